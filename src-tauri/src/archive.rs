@@ -0,0 +1,41 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+// The tar shipped with Windows 10 and Linux distributions picks the compression from the archive suffix
+const TAR: &str = "tar";
+
+fn absolute(path: &str) -> Result<PathBuf, String> {
+    std::path::absolute(path).map_err(|e| e.to_string())
+}
+
+fn run(mut command: Command) -> Result<(), String> {
+    let output = command.output().map_err(|e| format!("Failed to run {}: {}", TAR, e))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(String::from_utf8_lossy(&output.stderr).trim().to_string())
+    }
+}
+
+// Each source is added by name from its own folder, so the archive holds relative paths only.
+// Names are prefixed with "./" so that one starting with "-" is never read as an option
+pub fn create(archive: &str, sources: &[String]) -> Result<(), String> {
+    let mut command = Command::new(TAR);
+    command.arg("-a").arg("-c").arg("-f").arg(absolute(archive)?);
+    for source in sources {
+        let path = absolute(source)?;
+        let name = path.file_name().ok_or(format!("Cannot archive {}", source))?;
+        let parent = path.parent().unwrap_or(Path::new("/")).to_path_buf();
+        command.arg("-C").arg(parent).arg(Path::new(".").join(name));
+    }
+    run(command)
+}
+
+pub fn extract(archive: &str, destination: &str) -> Result<(), String> {
+    std::fs::create_dir_all(destination).map_err(|e| e.to_string())?;
+    let mut command = Command::new(TAR);
+    command.arg("-x").arg("-f").arg(absolute(archive)?).arg("-C").arg(absolute(destination)?);
+    run(command)
+}
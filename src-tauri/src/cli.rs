@@ -0,0 +1,172 @@
+use std::collections::HashMap;
+
+const USAGE: &str = "Usage: explite [OPTIONS] [PATH]...
+
+Options:
+  --select <FILE>      Open the folder containing FILE and select it
+  --new-window         Open in a new window even if explite is running
+  --tab                Open in a new tab of the active window
+  --search <QUERY>     Search the opened folder for QUERY
+  --view <list|grid>   Show the opened folder as a list or a grid
+  --print-dirs         Print the folders open in the running instance and exit
+  -h, --help           Print this help and exit
+  -V, --version        Print the version and exit";
+
+#[derive(Debug, Clone, Default)]
+pub struct CliArgs {
+    pub paths: Vec<String>,
+    pub options: HashMap<String, String>,
+}
+
+enum Command {
+    Open(CliArgs),
+    Help,
+    Version,
+    PrintDirs,
+}
+
+// Handles the informational commands here and returns what should be opened
+pub fn handle(app: &tauri::App) -> CliArgs {
+    let command = match parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("explite: {}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        Command::Open(args) => args,
+        Command::Help => {
+            println!("{}", USAGE);
+            std::process::exit(0);
+        }
+        Command::Version => {
+            println!("explite {}", app.package_info().version);
+            std::process::exit(0);
+        }
+        Command::PrintDirs => match crate::session::directories(&app.config().identifier) {
+            Ok(directories) => {
+                for directory in directories {
+                    println!("{}", directory);
+                }
+                std::process::exit(0);
+            }
+            Err(_) => {
+                eprintln!("explite: No running instance found");
+                std::process::exit(1);
+            }
+        },
+    }
+}
+
+fn parse(args: impl IntoIterator<Item = String>) -> Result<Command, String> {
+    let mut result = CliArgs::default();
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Command::Help),
+            "-V" | "--version" => return Ok(Command::Version),
+            "--print-dirs" => return Ok(Command::PrintDirs),
+            "--new-window" => {
+                result.options.insert("new-window".to_string(), "true".to_string());
+            }
+            "--tab" => {
+                result.options.insert("tab".to_string(), "true".to_string());
+            }
+            "--select" => {
+                let file = args.next().ok_or("--select requires a file")?;
+                result.paths.push(absolute_url(&file));
+                result.options.insert("reveal".to_string(), "true".to_string());
+            }
+            "--search" => {
+                let query = args.next().ok_or("--search requires a query")?;
+                result.options.insert("search".to_string(), query);
+            }
+            "--view" => {
+                let view = args.next().ok_or("--view requires list or grid")?;
+                if view != "list" && view != "grid" {
+                    return Err(format!("Unknown view \"{}\"", view));
+                }
+                result.options.insert("view".to_string(), view);
+            }
+            // Everything after "--" is a path even if it starts with "-"
+            "--" => {
+                result.paths.extend(args.by_ref().map(|path| absolute_url(&path)));
+            }
+            _ if arg.starts_with('-') => return Err(format!("Unknown option \"{}\"", arg)),
+            _ => result.paths.push(absolute_url(&arg)),
+        }
+    }
+
+    Ok(Command::Open(result))
+}
+
+// Relative paths are resolved against the working directory of the launching process
+fn absolute_url(url: &str) -> String {
+    if url.contains("://") {
+        return url.to_string();
+    }
+    std::path::absolute(url).map(|path| path.to_string_lossy().to_string()).unwrap_or(url.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_open(args: &[&str]) -> CliArgs {
+        match parse(args.iter().map(|arg| arg.to_string())) {
+            Ok(Command::Open(args)) => args,
+            _ => panic!("{:?} was not parsed as paths to open", args),
+        }
+    }
+
+    #[test]
+    fn paths_are_made_absolute() {
+        let args = parse_open(&["docs", "/tmp", "sftp://host/home"]);
+        assert_eq!(args.paths, vec![std::env::current_dir().unwrap().join("docs").to_string_lossy().to_string(), "/tmp".to_string(), "sftp://host/home".to_string()]);
+        assert!(args.options.is_empty());
+    }
+
+    #[test]
+    fn options_are_collected() {
+        let args = parse_open(&["--tab", "--new-window", "--search", "a b", "--view", "grid", "/tmp"]);
+        assert_eq!(args.paths, vec!["/tmp".to_string()]);
+        assert_eq!(args.options.get("tab").map(String::as_str), Some("true"));
+        assert_eq!(args.options.get("new-window").map(String::as_str), Some("true"));
+        assert_eq!(args.options.get("search").map(String::as_str), Some("a b"));
+        assert_eq!(args.options.get("view").map(String::as_str), Some("grid"));
+    }
+
+    #[test]
+    fn select_reveals_the_file() {
+        let args = parse_open(&["--select", "/tmp/a.txt"]);
+        assert_eq!(args.paths, vec!["/tmp/a.txt".to_string()]);
+        assert_eq!(args.options.get("reveal").map(String::as_str), Some("true"));
+    }
+
+    #[test]
+    fn paths_after_double_dash_are_not_options() {
+        let args = parse_open(&["--", "/tmp/-a", "--tab"]);
+        let tab = std::env::current_dir().unwrap().join("--tab").to_string_lossy().to_string();
+        assert_eq!(args.paths, vec!["/tmp/-a".to_string(), tab]);
+        assert!(args.options.is_empty());
+    }
+
+    #[test]
+    fn informational_commands_are_recognized() {
+        assert!(matches!(parse(["-h".to_string()]), Ok(Command::Help)));
+        assert!(matches!(parse(["/tmp".to_string(), "--version".to_string()]), Ok(Command::Version)));
+        assert!(matches!(parse(["--print-dirs".to_string()]), Ok(Command::PrintDirs)));
+    }
+
+    #[test]
+    fn invalid_arguments_are_errors() {
+        assert!(parse(["--view".to_string(), "icons".to_string()]).is_err());
+        assert!(parse(["--view".to_string()]).is_err());
+        assert!(parse(["--select".to_string()]).is_err());
+        assert!(parse(["--search".to_string()]).is_err());
+        assert!(parse(["--unknown".to_string()]).is_err());
+    }
+}
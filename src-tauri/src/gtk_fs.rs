@@ -1,4 +1,9 @@
-use crate::CopyInfo;
+use crate::{
+    progress::DiskUsages,
+    remote::{TransferControl, TransferJob, TransferStatus},
+    xdg_trash::{self, Conflict, TrashEntry},
+    CopyInfo,
+};
 use gtk::{
     gdk_pixbuf::{traits::PixbufLoaderExt, InterpType, PixbufLoader},
     gio,
@@ -7,7 +12,8 @@ use gtk::{
     traits::{BoxExt, ButtonExt, CssProviderExt, DialogExt, GtkWindowExt, HeaderBarExt, LabelExt, OrientableExt, ProgressBarExt, StyleContextExt, ToggleButtonExt, WidgetExt},
     Align, CssProvider, Dialog, Label, Orientation, ProgressBar, ResponseType, STYLE_PROVIDER_PRIORITY_APPLICATION,
 };
-use smol::channel::Sender;
+use smol::channel::{Receiver, Sender};
+use std::{sync::Arc, time::Duration};
 use zouni::fs::{FileOperation, OperationStatus, Response};
 
 pub fn copy(payload: CopyInfo) -> Result<(), String> {
@@ -20,11 +26,6 @@ pub fn mv(payload: CopyInfo) -> Result<(), String> {
     Ok(())
 }
 
-pub fn trash(payload: Vec<String>) -> Result<(), String> {
-    operate(FileOperation::Trash, payload, None);
-    Ok(())
-}
-
 pub fn delete(payload: Vec<String>) -> Result<(), String> {
     operate(FileOperation::Delete, payload, None);
     Ok(())
@@ -32,7 +33,7 @@ pub fn delete(payload: Vec<String>) -> Result<(), String> {
 
 fn operate(operation: FileOperation, froms: Vec<String>, to: Option<String>) {
     let (pause_tx, pause_rx) = smol::channel::bounded::<bool>(1);
-    let confirm_dialog = create_replace_confirm_dialog();
+    let confirm_dialog = create_replace_confirm_dialog(false);
     let widget = create_progress_dialog(&operation, "Preparing...", to.as_ref().unwrap_or(&String::new()), pause_tx);
     let now = std::time::Instant::now();
     let mut skip_or_replace = ReplaceOrSkip::Replace;
@@ -79,13 +80,13 @@ fn operate(operation: FileOperation, froms: Vec<String>, to: Option<String>) {
                 Response::Proceed
             }
             OperationStatus::Confirm(target) => {
-                if skip_or_replace != ReplaceOrSkip::ReplaceAll && skip_or_replace != ReplaceOrSkip::SkipAll {
+                if !skip_or_replace.applies_to_all() {
                     skip_or_replace = confirm_dialog.confirm(&target).await;
                 }
 
                 match skip_or_replace {
                     ReplaceOrSkip::Skip | ReplaceOrSkip::SkipAll => Response::Skip,
-                    ReplaceOrSkip::Replace | ReplaceOrSkip::ReplaceAll => Response::Replace,
+                    ReplaceOrSkip::Replace | ReplaceOrSkip::ReplaceAll | ReplaceOrSkip::KeepBoth | ReplaceOrSkip::KeepBothAll => Response::Replace,
                     ReplaceOrSkip::Cancel => {
                         widget.close();
                         Response::Cancel
@@ -119,38 +120,179 @@ fn operate(operation: FileOperation, froms: Vec<String>, to: Option<String>) {
     });
 }
 
-fn update_progress(widget: &FileOperationDialog, operation: &FileOperation, usages: &mut DiskUsages) {
-    let (messag, current, total) = match operation {
-        FileOperation::Copy => (
-            "Copying",
-            if usages.total_size == 0 {
-                usages.processed_count
-            } else {
-                usages.processed_size
-            },
-            if usages.total_size == 0 {
-                usages.total_count
-            } else {
-                usages.total_size
-            },
-        ),
-        FileOperation::Move => (
-            "Moving",
-            if usages.total_size == 0 {
-                usages.processed_count
-            } else {
-                usages.processed_size
+enum TransferMessage {
+    Status(TransferStatus),
+    Pause(bool),
+    Tick,
+    Closed,
+}
+
+// Runs a remote transfer on a worker thread and reports to the same progress/confirm dialogs as local operations
+pub fn transfer(job: TransferJob) -> Result<(), String> {
+    let (status_tx, status_rx) = smol::channel::unbounded::<TransferStatus>();
+    let (response_tx, response_rx) = smol::channel::bounded::<ReplaceOrSkip>(1);
+    let control = Arc::new(TransferControl::default());
+
+    report_progress(job.operation.into(), &job.to, false, status_rx, response_tx, control.clone());
+
+    std::thread::spawn(move || {
+        job.run(&control, |status| {
+            let needs_response = matches!(status, TransferStatus::Confirm(_));
+            if status_tx.send_blocking(status).is_err() {
+                return Response::Cancel;
+            }
+            if !needs_response {
+                return Response::Proceed;
+            }
+            match response_rx.recv_blocking().unwrap_or(ReplaceOrSkip::Cancel) {
+                ReplaceOrSkip::Skip | ReplaceOrSkip::SkipAll => Response::Skip,
+                ReplaceOrSkip::Cancel => Response::Cancel,
+                _ => Response::Replace,
+            }
+        });
+    });
+
+    Ok(())
+}
+
+// Restores trashed items into the destination folder with the same dialogs as a move
+pub fn restore_to(entries: Vec<TrashEntry>, destination: String) -> Result<(), String> {
+    let (status_tx, status_rx) = smol::channel::unbounded::<TransferStatus>();
+    let (response_tx, response_rx) = smol::channel::bounded::<ReplaceOrSkip>(1);
+    let control = Arc::new(TransferControl::default());
+
+    report_progress(FileOperation::Move, &destination, true, status_rx, response_tx, control.clone());
+
+    std::thread::spawn(move || {
+        let result = xdg_trash::restore_to(
+            &entries,
+            std::path::Path::new(&destination),
+            &control,
+            |status| {
+                let _ = status_tx.send_blocking(status);
             },
-            if usages.total_size == 0 {
-                usages.total_count
-            } else {
-                usages.total_size
+            |target| {
+                if status_tx.send_blocking(TransferStatus::Confirm(target.to_string_lossy().to_string())).is_err() {
+                    return Conflict::Cancel;
+                }
+                match response_rx.recv_blocking().unwrap_or(ReplaceOrSkip::Cancel) {
+                    ReplaceOrSkip::Replace | ReplaceOrSkip::ReplaceAll => Conflict::Replace,
+                    ReplaceOrSkip::Skip | ReplaceOrSkip::SkipAll => Conflict::Skip,
+                    ReplaceOrSkip::KeepBoth | ReplaceOrSkip::KeepBothAll => Conflict::KeepBoth,
+                    ReplaceOrSkip::Cancel => Conflict::Cancel,
+                }
             },
-        ),
-        FileOperation::Delete => ("Deleting", usages.processed_count, usages.total_count),
-        FileOperation::Trash => ("Trashing", usages.processed_count, usages.total_count),
+        );
+        let _ = status_tx.send_blocking(match result {
+            Ok(_) => TransferStatus::Finished,
+            Err(e) => TransferStatus::Error(e),
+        });
+    });
+
+    Ok(())
+}
+
+// Drives the progress and confirm dialogs on the main thread from statuses sent by a worker thread
+pub(crate) fn report_progress(operation: FileOperation, to: &str, allow_keep_both: bool, status_rx: Receiver<TransferStatus>, response_tx: Sender<ReplaceOrSkip>, control: Arc<TransferControl>) {
+    let (pause_tx, pause_rx) = smol::channel::bounded::<bool>(1);
+    let confirm_dialog = create_replace_confirm_dialog(allow_keep_both);
+    let widget = create_progress_dialog(&operation, "Preparing...", to, pause_tx);
+
+    glib::spawn_future_local(async move {
+        let now = std::time::Instant::now();
+        let mut skip_or_replace = ReplaceOrSkip::Replace;
+        let mut usages = DiskUsages::default();
+        let mut shown = false;
+
+        loop {
+            let message = smol::future::race(
+                async { status_rx.recv().await.map(TransferMessage::Status).unwrap_or(TransferMessage::Closed) },
+                smol::future::race(async { pause_rx.recv().await.map(TransferMessage::Pause).unwrap_or(TransferMessage::Tick) }, async {
+                    smol::Timer::after(Duration::from_millis(200)).await;
+                    TransferMessage::Tick
+                }),
+            )
+            .await;
+
+            if widget.cancelled() {
+                control.cancel();
+                widget.close();
+                return;
+            }
+
+            let status = match message {
+                TransferMessage::Status(status) => status,
+                TransferMessage::Pause(pause) => {
+                    control.set_paused(pause);
+                    continue;
+                }
+                TransferMessage::Tick => continue,
+                TransferMessage::Closed => {
+                    widget.close();
+                    return;
+                }
+            };
+
+            usages.update(&status);
+            match status {
+                TransferStatus::Ready(_, _) => {
+                    widget.progress(0.0);
+                    update_progress(&widget, &operation, &mut usages);
+                }
+                TransferStatus::Start(file) => {
+                    widget.set_from_name(&file);
+                }
+                TransferStatus::Progress(_) => {
+                    // Show widget after 3 seconds
+                    if !shown && now.elapsed().as_secs() > 3 {
+                        widget.show();
+                        shown = true;
+                    }
+                    update_progress(&widget, &operation, &mut usages);
+                }
+                TransferStatus::Confirm(target) => {
+                    if !skip_or_replace.applies_to_all() {
+                        skip_or_replace = confirm_dialog.confirm(&target).await;
+                    }
+
+                    if skip_or_replace == ReplaceOrSkip::Cancel {
+                        control.cancel();
+                        widget.close();
+                    }
+                    let _ = response_tx.send(skip_or_replace.clone()).await;
+                }
+                TransferStatus::End => {
+                    update_progress(&widget, &operation, &mut usages);
+                }
+                TransferStatus::Finished => {
+                    widget.close();
+                    return;
+                }
+                TransferStatus::Error(e) => {
+                    widget.close();
+                    zouni::dialog::message(zouni::dialog::MessageDialogOptions {
+                        title: None,
+                        kind: Some(zouni::dialog::MessageDialogKind::Error),
+                        buttons: vec!["OK".to_string()],
+                        message: e,
+                        cancel_id: None,
+                    })
+                    .await;
+                    return;
+                }
+            }
+        }
+    });
+}
+
+pub(crate) fn update_progress(widget: &FileOperationDialog, operation: &FileOperation, usages: &mut DiskUsages) {
+    let messag = match operation {
+        FileOperation::Copy => "Copying",
+        FileOperation::Move => "Moving",
+        FileOperation::Delete => "Deleting",
+        FileOperation::Trash => "Trashing",
     };
-    usages.progress = current as f64 / total as f64;
+    usages.update_progress(matches!(operation, FileOperation::Copy | FileOperation::Move));
     let percent = usages.progress * 100.0;
     widget.set_title(&format!("{}% complete", percent.ceil()));
     widget.progress(usages.progress);
@@ -158,17 +300,8 @@ fn update_progress(widget: &FileOperationDialog, operation: &FileOperation, usag
     widget.set_message(&format!("{messag} {}/{} items ", usages.processed_count, usages.total_count));
 }
 
-#[derive(Default, Debug, Copy, Clone)]
-pub struct DiskUsages {
-    pub total_size: u64,
-    pub total_count: u64,
-    pub processed_count: u64,
-    pub processed_size: u64,
-    pub progress: f64,
-}
-
 #[allow(dead_code)]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct FileOperationDialog {
     pub(crate) dialog: Dialog,
     progress_bar: ProgressBar,
@@ -454,13 +587,23 @@ pub enum ReplaceOrSkip {
     ReplaceAll,
     Skip,
     SkipAll,
+    KeepBoth,
+    KeepBothAll,
     Cancel,
 }
 
+impl ReplaceOrSkip {
+    pub(crate) fn applies_to_all(&self) -> bool {
+        *self == ReplaceOrSkip::ReplaceAll || *self == ReplaceOrSkip::SkipAll || *self == ReplaceOrSkip::KeepBothAll
+    }
+}
+
 const REPLACE: u16 = 0;
 const REPLACE_ALL: u16 = 1;
 const SKIP: u16 = 2;
 const SKIP_ALL: u16 = 3;
+const KEEP_BOTH: u16 = 4;
+const KEEP_BOTH_ALL: u16 = 5;
 fn response_to_enum(response: &ResponseType) -> ReplaceOrSkip {
     match response {
         ResponseType::Other(value) => match *value {
@@ -468,6 +611,8 @@ fn response_to_enum(response: &ResponseType) -> ReplaceOrSkip {
             REPLACE_ALL => ReplaceOrSkip::ReplaceAll,
             SKIP => ReplaceOrSkip::Skip,
             SKIP_ALL => ReplaceOrSkip::SkipAll,
+            KEEP_BOTH => ReplaceOrSkip::KeepBoth,
+            KEEP_BOTH_ALL => ReplaceOrSkip::KeepBothAll,
             _ => ReplaceOrSkip::Skip,
         },
         ResponseType::Cancel => ReplaceOrSkip::Cancel,
@@ -485,7 +630,7 @@ impl FileReplaceDialog {
     }
 }
 
-pub(crate) fn create_replace_confirm_dialog() -> FileReplaceDialog {
+pub(crate) fn create_replace_confirm_dialog(allow_keep_both: bool) -> FileReplaceDialog {
     let dialog = Dialog::new();
     dialog.set_destroy_with_parent(true);
 
@@ -583,8 +728,14 @@ pub(crate) fn create_replace_confirm_dialog() -> FileReplaceDialog {
     let cancel = gtk::Button::with_label("Cancel");
     cancel.set_widget_name("confirm-button");
     cancel.style_context().add_provider(&css_provider, STYLE_PROVIDER_PRIORITY_APPLICATION);
+    let keep_both = gtk::Button::with_label("Keep both");
+    keep_both.set_widget_name("confirm-button");
+    keep_both.style_context().add_provider(&css_provider, STYLE_PROVIDER_PRIORITY_APPLICATION);
     buttons.pack_start(&overwrite, false, false, 5);
     buttons.pack_start(&skip, false, false, 5);
+    if allow_keep_both {
+        buttons.pack_start(&keep_both, false, false, 5);
+    }
     buttons.pack_start(&cancel, false, false, 5);
     content_area.pack_start(&buttons, true, true, 5);
 
@@ -606,6 +757,15 @@ pub(crate) fn create_replace_confirm_dialog() -> FileReplaceDialog {
         gio::glib::Propagation::Proceed
     }));
 
+    keep_both.connect_button_release_event(clone!(@weak dialog, @strong checkbox => @default-return gio::glib::Propagation::Proceed, move |_, _| {
+        if checkbox.is_active() {
+            dialog.response(ResponseType::Other(KEEP_BOTH_ALL));
+        } else {
+            dialog.response(ResponseType::Other(KEEP_BOTH));
+        }
+        gio::glib::Propagation::Proceed
+    }));
+
     cancel.connect_button_release_event(clone!(@weak dialog => @default-return gio::glib::Propagation::Proceed, move |_, _| {
         dialog.response(ResponseType::Cancel);
         gio::glib::Propagation::Proceed
@@ -1,3 +1,4 @@
+use crate::thumbnailer::Thumbnailer;
 use ffmpeg_next::{
     format::{input, Pixel},
     media::Type,
@@ -13,7 +14,7 @@ use rs_vips::{
 use std::{
     collections::HashMap,
     ffi::{c_char, c_void, CStr, CString},
-    fs::{create_dir, set_permissions, Permissions},
+    fs::{set_permissions, Permissions},
     os::unix::fs::PermissionsExt,
     path::{Path, PathBuf},
 };
@@ -22,30 +23,79 @@ use url::Url;
 enum SourceType {
     Picture,
     Video,
+    External(&'static Thumbnailer),
 }
 
 const THUMB_URI: &str = "png-comment-0-Thumb::URI";
 const THUMB_MTIME: &str = "png-comment-1-Thumb::MTime";
 const THUMB_SOFTWARE: &str = "png-comment-2-Software";
-const NORMAL: i32 = 128;
-const LARGE: i32 = 256;
+const FAIL_DIRECTORY: &str = "fail/explite";
+// 1x1 transparent PNG
+const FAILURE_PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44, 0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f, 0x15, 0xc4,
+    0x89, 0x00, 0x00, 0x00, 0x0b, 0x49, 0x44, 0x41, 0x54, 0x78, 0x9c, 0x63, 0x60, 0x00, 0x02, 0x00, 0x00, 0x05, 0x00, 0x01, 0x7a, 0x5e, 0xab, 0x3f, 0x00, 0x00, 0x00, 0x00, 0x49, 0x45, 0x4e, 0x44,
+    0xae, 0x42, 0x60, 0x82,
+];
 const DIRECTORY_PERM: u32 = 0o700;
 const FILE_PERM: u32 = 0o600;
 
-// Creates thumbnails(128 and 256)
-pub fn video_thumbnail<P: AsRef<Path>>(file: P, software: Option<&str>) -> Result<(Vec<u8>, Vec<u8>), String> {
-    make_thumbnail(file, SourceType::Video, software)
+// Size directories defined by the thumbnail spec
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThumbnailSize {
+    Normal,
+    Large,
+    XLarge,
+    XXLarge,
 }
 
-// Creates thumbnails(128 and 256)
-pub fn image_thumbnail<P: AsRef<Path>>(file: P, software: Option<&str>) -> Result<(Vec<u8>, Vec<u8>), String> {
-    make_thumbnail(file, SourceType::Picture, software)
+impl ThumbnailSize {
+    const ALL: [ThumbnailSize; 4] = [ThumbnailSize::Normal, ThumbnailSize::Large, ThumbnailSize::XLarge, ThumbnailSize::XXLarge];
+
+    // The smallest size that covers the request so that thumbnails are never scaled up on screen
+    pub fn nearest(width: u32, height: u32) -> Self {
+        let requested = width.max(height) as i32;
+        Self::ALL.into_iter().find(|size| size.pixels() >= requested).unwrap_or(ThumbnailSize::XXLarge)
+    }
+
+    fn pixels(self) -> i32 {
+        match self {
+            ThumbnailSize::Normal => 128,
+            ThumbnailSize::Large => 256,
+            ThumbnailSize::XLarge => 512,
+            ThumbnailSize::XXLarge => 1024,
+        }
+    }
+
+    fn directory(self) -> &'static str {
+        match self {
+            ThumbnailSize::Normal => "normal",
+            ThumbnailSize::Large => "large",
+            ThumbnailSize::XLarge => "x-large",
+            ThumbnailSize::XXLarge => "xx-large",
+        }
+    }
+}
+
+pub fn video_thumbnail<P: AsRef<Path>>(file: P, size: ThumbnailSize, software: Option<&str>) -> Result<Vec<u8>, String> {
+    make_thumbnail(file, SourceType::Video, size, software)
 }
 
-fn make_thumbnail<P: AsRef<Path>>(file: P, source_type: SourceType, software: Option<&str>) -> Result<(Vec<u8>, Vec<u8>), String> {
+pub fn image_thumbnail<P: AsRef<Path>>(file: P, size: ThumbnailSize, software: Option<&str>) -> Result<Vec<u8>, String> {
+    make_thumbnail(file, SourceType::Picture, size, software)
+}
+
+// Uses the thumbnailer installed for the MIME type
+pub fn external_thumbnail<P: AsRef<Path>>(file: P, mime_type: &str, size: ThumbnailSize, software: Option<&str>) -> Result<Vec<u8>, String> {
+    let thumbnailer = crate::thumbnailer::find(mime_type).ok_or(format!("No thumbnailer for {}", mime_type))?;
+    // Nothing else can decode these, so the failure is final
+    make_thumbnail(file.as_ref(), SourceType::External(thumbnailer), size, software).inspect_err(|_| record_failure(file.as_ref(), software))
+}
+
+// Creates only the requested size. Failures are recorded by the caller once every decoder has failed
+fn make_thumbnail<P: AsRef<Path>>(file: P, source_type: SourceType, size: ThumbnailSize, software: Option<&str>) -> Result<Vec<u8>, String> {
     let _ = Vips::init("mkthumb");
 
-    let url = Url::from_file_path(file.as_ref()).unwrap();
+    let url = Url::from_file_path(file.as_ref()).map_err(|_| "Invalid file path".to_string())?;
     let info = File::for_path(file.as_ref()).query_info("access::can-read,time::*", FileQueryInfoFlags::NOFOLLOW_SYMLINKS, Cancellable::NONE).map_err(|e| e.message().to_string())?;
     // the original image file is readable. If it is not, the program should not attempt to read a thumbnail from the cache, and it should not save any information in the cache
     if !info.boolean("access::can-read") {
@@ -53,111 +103,135 @@ fn make_thumbnail<P: AsRef<Path>>(file: P, source_type: SourceType, software: Op
     }
     let mtime = info.attribute_uint64("time::modified").to_string();
 
-    let mut hasher = Md5::new();
-    hasher.update(url.as_str().as_bytes());
+    // Files that failed before are skipped until they are modified
+    let failure = prepare_cache_file(&url, FAIL_DIRECTORY)?;
+    if !must_recreate(&failure, &url, &mtime) {
+        return Err("Thumbnail could not be created before".to_string());
+    }
 
-    let result = hasher.finalize();
+    let thumbnail = prepare_cache_file(&url, size.directory())?;
+    if !must_recreate(&thumbnail, &url, &mtime) {
+        return std::fs::read(&thumbnail).map_err(|e| e.to_string());
+    }
 
-    let fname = format!("{:x}.png", result);
+    let data = match source_type {
+        SourceType::Picture => from_image(&url, file.as_ref(), size, &mtime, software),
+        SourceType::Video => from_video(&url, file.as_ref(), size, &mtime, software),
+        SourceType::External(thumbnailer) => from_external(&url, thumbnailer, file.as_ref(), size, &mtime, software),
+    }?;
+    write_cache_file(&thumbnail, &data)?;
 
-    let cache_directory_candidate = if let Ok(cache_home) = std::env::var("XDG_CACHE_HOME") {
-        Some(PathBuf::from(format!("{cache_home}/thumbnails")))
-    } else if let Ok(home) = std::env::var("HOME") {
-        Some(PathBuf::from(format!("{home}/.cache/thumbnails")))
-    } else {
-        None
-    };
+    if failure.exists() {
+        let _ = std::fs::remove_file(&failure);
+    }
 
-    let cache_directory = if let Some(cache_directory) = cache_directory_candidate {
-        cache_directory
-    } else {
-        return Err("Thumbnail cache directory not found".to_string());
-    };
+    Ok(data)
+}
 
-    if !cache_directory.exists() {
-        create_dir(&cache_directory).map_err(|e| e.to_string())?;
-        let permissions = Permissions::from_mode(DIRECTORY_PERM);
-        set_permissions(&cache_directory, permissions).map_err(|e| e.to_string())?;
-    }
+// Returns true while the failure recorded for the file is still valid
+pub fn has_failed<P: AsRef<Path>>(file: P) -> bool {
+    let _ = Vips::init("mkthumb");
 
-    let (normal, large) = (cache_directory.join("normal").join(&fname), cache_directory.join("large").join(&fname));
+    let Some((url, mtime)) = Url::from_file_path(file.as_ref()).ok().zip(modified_seconds(file.as_ref())) else {
+        return false;
+    };
+    cache_file(&url, FAIL_DIRECTORY).is_ok_and(|failure| !must_recreate(&failure, &url, &mtime))
+}
 
-    if !normal.exists() {
-        create_dir(&normal).map_err(|e| e.to_string())?;
-        let permissions = Permissions::from_mode(DIRECTORY_PERM);
-        set_permissions(&normal, permissions).map_err(|e| e.to_string())?;
-    }
+// The spec stores failures as an empty PNG carrying the same metadata as a thumbnail
+pub fn record_failure<P: AsRef<Path>>(file: P, software: Option<&str>) {
+    let _ = Vips::init("mkthumb");
 
-    if !large.exists() {
-        create_dir(&large).map_err(|e| e.to_string())?;
-        let permissions = Permissions::from_mode(DIRECTORY_PERM);
-        set_permissions(&large, permissions).map_err(|e| e.to_string())?;
+    let Some((url, mtime)) = Url::from_file_path(file.as_ref()).ok().zip(modified_seconds(file.as_ref())) else {
+        return;
+    };
+    let Ok(failure) = prepare_cache_file(&url, FAIL_DIRECTORY) else {
+        return;
+    };
+    let data = VipsImage::new_from_buffer(FAILURE_PNG, "").map_err(map_vips_error).and_then(|mut image| {
+        set_image_data(&mut image, &url, &mtime, software)?;
+        image.pngsave_buffer().map_err(map_vips_error)
+    });
+    if let Ok(data) = data {
+        let _ = write_cache_file(&failure, &data);
     }
+}
 
-    let should_create_large = if large.exists() {
-        must_recreate(&large, &mtime).unwrap_or_default()
-    } else {
-        true
-    };
+fn modified_seconds(file: &Path) -> Option<String> {
+    Some(std::fs::metadata(file).ok()?.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?.as_secs().to_string())
+}
 
-    let size256 = if should_create_large {
-        match source_type {
-            SourceType::Picture => large_from_image(&url, file.as_ref().to_path_buf(), &mtime, software)?,
-            SourceType::Video => large_from_video(&url, file.as_ref().to_path_buf(), &mtime, software)?,
-        }
+pub fn cache_directory() -> Result<PathBuf, String> {
+    if let Ok(cache_home) = std::env::var("XDG_CACHE_HOME") {
+        Ok(PathBuf::from(format!("{cache_home}/thumbnails")))
+    } else if let Ok(home) = std::env::var("HOME") {
+        Ok(PathBuf::from(format!("{home}/.cache/thumbnails")))
     } else {
-        Vec::new()
-    };
-
-    if !size256.is_empty() {
-        std::fs::write(&large, &size256).map_err(|e| e.to_string())?;
-        let permissions = Permissions::from_mode(FILE_PERM);
-        set_permissions(&large, permissions).map_err(|e| e.to_string())?;
+        Err("Thumbnail cache directory not found".to_string())
     }
+}
 
-    let should_create_normal = if normal.exists() {
-        must_recreate(&normal, &mtime).unwrap_or_default()
-    } else {
-        true
-    };
+// Cache files are named by the MD5 of the file URI
+fn cache_file(url: &Url, size_directory: &str) -> Result<PathBuf, String> {
+    let mut hasher = Md5::new();
+    hasher.update(url.as_str().as_bytes());
+    let fname = format!("{:x}.png", hasher.finalize());
+    Ok(cache_directory()?.join(size_directory).join(fname))
+}
 
-    let size128 = if should_create_normal {
-        match source_type {
-            SourceType::Picture => normal_from_image(&url, &size256, file.as_ref().to_path_buf(), &mtime, software)?,
-            SourceType::Video => normal_from_video(&url, &size256, file.as_ref().to_path_buf(), &mtime, software)?,
+fn prepare_cache_file(url: &Url, size_directory: &str) -> Result<PathBuf, String> {
+    let root = cache_directory()?;
+    let file = cache_file(url, size_directory)?;
+    if let Some(directory) = file.parent() {
+        if !directory.exists() {
+            std::fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+            for ancestor in directory.ancestors().take_while(|ancestor| ancestor.starts_with(&root)) {
+                set_permissions(ancestor, Permissions::from_mode(DIRECTORY_PERM)).map_err(|e| e.to_string())?;
+            }
         }
-    } else {
-        Vec::new()
-    };
-
-    if !size128.is_empty() {
-        std::fs::write(&normal, &size128).map_err(|e| e.to_string())?;
-        let permissions = Permissions::from_mode(FILE_PERM);
-        set_permissions(&normal, permissions).map_err(|e| e.to_string())?;
     }
+    Ok(file)
+}
+
+fn write_cache_file(file: &Path, data: &[u8]) -> Result<(), String> {
+    std::fs::write(file, data).map_err(|e| e.to_string())?;
+    set_permissions(file, Permissions::from_mode(FILE_PERM)).map_err(|e| e.to_string())
+}
+
+// Returns the cached thumbnail when it is up to date with the file
+pub fn cached<P: AsRef<Path>>(file: P, size: ThumbnailSize) -> Option<PathBuf> {
+    let _ = Vips::init("mkthumb");
 
-    Ok((size128, size256))
+    let url = Url::from_file_path(file.as_ref()).ok()?;
+    let mtime = modified_seconds(file.as_ref())?;
+    let thumbnail = cache_file(&url, size.directory()).ok()?;
+    (!must_recreate(&thumbnail, &url, &mtime)).then_some(thumbnail)
 }
 
-fn must_recreate(large: &PathBuf, current_mtime: &String) -> Result<bool, String> {
-    let image = VipsImage::new_from_file(large).map_err(map_vips_error)?;
+fn read_comments(thumbnail: &Path) -> Result<HashMap<String, String>, String> {
+    let image = VipsImage::new_from_file(thumbnail).map_err(map_vips_error)?;
     let mut comments = Box::new(HashMap::<String, String>::new());
     let comments_ptr: *mut c_void = &mut *comments as *mut _ as *mut c_void;
     unsafe { vips_image_map(image.as_mut_ptr(), Some(read_pngcomment), comments_ptr) };
+    Ok(*comments)
+}
 
-    if let Some(uri) = comments.get("Thumb::URI") {
-        if uri != &uri.to_string() {
-            return Ok(false);
-        }
-    }
+// Returns the URI of the file the thumbnail was made from
+pub fn source_uri(thumbnail: &Path) -> Option<String> {
+    let _ = Vips::init("mkthumb");
+    read_comments(thumbnail).ok()?.remove("Thumb::URI")
+}
 
-    if let Some(mtime) = comments.get("Thumb::MTime") {
-        if mtime == current_mtime {
-            return Ok(false);
-        }
+fn must_recreate(thumbnail: &Path, url: &Url, current_mtime: &str) -> bool {
+    if !thumbnail.exists() {
+        return true;
     }
 
-    Ok(true)
+    let Ok(comments) = read_comments(thumbnail) else {
+        return true;
+    };
+
+    comments.get("Thumb::URI").is_some_and(|uri| uri != url.as_str()) || comments.get("Thumb::MTime").map(String::as_str) != Some(current_mtime)
 }
 
 unsafe extern "C" fn read_pngcomment(image: *mut rs_vips::bindings::_VipsImage, field: *const c_char, _value: *mut GValue, data: *mut c_void) -> *mut c_void {
@@ -184,36 +258,24 @@ unsafe extern "C" fn read_pngcomment(image: *mut rs_vips::bindings::_VipsImage,
     std::ptr::null_mut()
 }
 
-fn large_from_image(url: &Url, source: PathBuf, mtime: &str, software: Option<&str>) -> Result<Vec<u8>, String> {
-    let mut image = VipsImage::new_from_file(source).map_err(map_vips_error)?.thumbnail_image(LARGE).map_err(map_vips_error)?;
-    set_image_data(&mut image, url, mtime, software)?;
-    image.pngsave_buffer().map_err(map_vips_error)
+fn from_image(url: &Url, source: &Path, size: ThumbnailSize, mtime: &str, software: Option<&str>) -> Result<Vec<u8>, String> {
+    let image = VipsImage::new_from_file(source).map_err(map_vips_error)?.thumbnail_image(size.pixels()).map_err(map_vips_error)?;
+    save_thumbnail(image, url, mtime, software)
 }
 
-fn normal_from_image(url: &Url, large: &[u8], source: PathBuf, mtime: &str, software: Option<&str>) -> Result<Vec<u8>, String> {
-    let mut image = if large.is_empty() {
-        VipsImage::new_from_file(source).map_err(map_vips_error)?.thumbnail_image(NORMAL).map_err(map_vips_error)?
-    } else {
-        VipsImage::new_from_buffer(large, "").map_err(map_vips_error)?.resize(0.5).map_err(map_vips_error)?
-    };
-    set_image_data(&mut image, url, mtime, software)?;
-    image.pngsave_buffer().map_err(map_vips_error)
+fn from_video(url: &Url, source: &Path, size: ThumbnailSize, mtime: &str, software: Option<&str>) -> Result<Vec<u8>, String> {
+    let buffer = create_video_thumbnail(source)?;
+    let image = VipsImage::new_from_buffer(&buffer, "").map_err(map_vips_error)?.thumbnail_image(size.pixels()).map_err(map_vips_error)?;
+    save_thumbnail(image, url, mtime, software)
 }
 
-fn large_from_video(url: &Url, source: PathBuf, mtime: &str, software: Option<&str>) -> Result<Vec<u8>, String> {
-    let buffer = create_video_thumbnail(source).map_err(|e| e.to_string())?;
-    let mut image = VipsImage::new_from_buffer(&buffer, "").map_err(map_vips_error)?.thumbnail_image(LARGE).map_err(map_vips_error)?;
-    set_image_data(&mut image, url, mtime, software)?;
-    image.pngsave_buffer().map_err(map_vips_error)
+fn from_external(url: &Url, thumbnailer: &Thumbnailer, source: &Path, size: ThumbnailSize, mtime: &str, software: Option<&str>) -> Result<Vec<u8>, String> {
+    let buffer = thumbnailer.run(url, source, size.pixels())?;
+    let image = VipsImage::new_from_buffer(&buffer, "").map_err(map_vips_error)?.thumbnail_image(size.pixels()).map_err(map_vips_error)?;
+    save_thumbnail(image, url, mtime, software)
 }
 
-fn normal_from_video(url: &Url, large: &[u8], source: PathBuf, mtime: &str, software: Option<&str>) -> Result<Vec<u8>, String> {
-    let mut image = if large.is_empty() {
-        let buffer = create_video_thumbnail(source).map_err(|e| e.to_string())?;
-        VipsImage::new_from_buffer(&buffer, "").map_err(map_vips_error)?.thumbnail_image(NORMAL).map_err(map_vips_error)?
-    } else {
-        VipsImage::new_from_buffer(large, "").map_err(map_vips_error)?.resize(0.5).map_err(map_vips_error)?
-    };
+fn save_thumbnail(mut image: VipsImage, url: &Url, mtime: &str, software: Option<&str>) -> Result<Vec<u8>, String> {
     set_image_data(&mut image, url, mtime, software)?;
     image.pngsave_buffer().map_err(map_vips_error)
 }
@@ -242,7 +304,7 @@ fn create_video_thumbnail<P: AsRef<Path>>(path: P) -> Result<Vec<u8>, String> {
             if data.kind() == ffmpeg_next::packet::side_data::Type::DisplayMatrix {
                 rotation = parse_display_matrix(data.data());
             } else {
-                rotation = input.metadata().get("rotate").unwrap_or("0").parse().unwrap();
+                rotation = input.metadata().get("rotate").and_then(|rotate| rotate.parse().ok()).unwrap_or(0);
             }
         }
 
@@ -0,0 +1,376 @@
+use crate::{
+    gtk_fs::{create_progress_dialog, create_replace_confirm_dialog, update_progress, ReplaceOrSkip},
+    progress::DiskUsages,
+    remote::{Dirent, FileAttribute, TransferOperation},
+};
+use gtk::{
+    gio::{self, prelude::*, Cancellable, File, FileCopyFlags, FileQueryInfoFlags, FileType, IOErrorEnum, MountMountFlags},
+    glib,
+};
+use std::{cell::RefCell, rc::Rc};
+
+const ATTRIBUTES: &str = "standard::*,time::*,access::*";
+// Children are enumerated in batches so that large folders do not need one request per item
+const ENUMERATE_BATCH: i32 = 64;
+// Schemes handled by the local file system or by explite's own providers
+const NATIVE_SCHEMES: [&str; 4] = ["file", "sftp", "dav", "davs"];
+
+pub fn is_uri(path: &str) -> bool {
+    match path.split_once("://") {
+        Some((scheme, _)) => !scheme.is_empty() && scheme.chars().all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.') && !NATIVE_SCHEMES.contains(&scheme),
+        None => false,
+    }
+}
+
+fn to_attribute(info: &gio::FileInfo) -> FileAttribute {
+    let to_ms = |attribute: &str| info.attribute_uint64(attribute) as f64 * 1000.0;
+    FileAttribute {
+        is_directory: info.file_type() == FileType::Directory || info.file_type() == FileType::Mountable,
+        is_file: info.file_type() == FileType::Regular,
+        is_hidden: info.is_hidden(),
+        is_read_only: info.has_attribute("access::can-write") && !info.boolean("access::can-write"),
+        is_symbolic_link: info.is_symlink(),
+        atime_ms: to_ms("time::access"),
+        mtime_ms: to_ms("time::modified"),
+        ctime_ms: to_ms("time::changed"),
+        birthtime_ms: to_ms("time::created"),
+        size: info.size().max(0) as u64,
+        link_path: info.symlink_target().map(|target| target.to_string_lossy().to_string()).unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+// Mounts the GVfs backend behind the uri when it is not mounted yet
+async fn ensure_mounted(file: &File) -> Result<(), String> {
+    match file.find_enclosing_mount(Cancellable::NONE) {
+        Ok(_) => Ok(()),
+        Err(e) if e.matches(IOErrorEnum::NotMounted) => {
+            let operation = gtk::MountOperation::new(None::<&gtk::Window>);
+            match file.mount_enclosing_volume_future(MountMountFlags::NONE, Some(&operation)).await {
+                Ok(_) => Ok(()),
+                Err(e) if e.matches(IOErrorEnum::AlreadyMounted) => Ok(()),
+                Err(e) => Err(e.message().to_string()),
+            }
+        }
+        // Some backends do not expose a mount but are still usable
+        Err(_) => Ok(()),
+    }
+}
+
+// Runs the future on the main thread where GTK mount dialogs are allowed
+async fn on_main_thread<T: Send + 'static>(app: &tauri::AppHandle, task: impl FnOnce() -> std::pin::Pin<Box<dyn std::future::Future<Output = T>>> + Send + 'static) -> Result<T, String> {
+    let (tx, rx) = smol::channel::bounded(1);
+    app.run_on_main_thread(move || {
+        glib::spawn_future_local(async move {
+            let _ = tx.send(task().await).await;
+        });
+    })
+    .map_err(|e| e.to_string())?;
+    rx.recv().await.map_err(|e| e.to_string())
+}
+
+pub async fn mount(app: &tauri::AppHandle, uri: &str) -> Result<(), String> {
+    let uri = uri.to_string();
+    on_main_thread(app, move || Box::pin(async move { ensure_mounted(&File::for_uri(&uri)).await })).await?
+}
+
+// Runs blocking GIO calls, which may wait on the network, on a GIO worker thread
+async fn blocking<T: Send + 'static>(task: impl FnOnce() -> T + Send + 'static) -> Result<T, String> {
+    gio::spawn_blocking(task).await.map_err(|_| "GIO worker failed".to_string())
+}
+
+pub async fn readdir(app: &tauri::AppHandle, uri: &str) -> Result<Vec<Dirent>, String> {
+    mount(app, uri).await?;
+    let directory = File::for_uri(uri);
+    blocking(move || list(&directory)).await?
+}
+
+fn list(directory: &File) -> Result<Vec<Dirent>, String> {
+    let parent_path = directory.uri().to_string();
+    let enumerator = directory.enumerate_children(ATTRIBUTES, FileQueryInfoFlags::NONE, Cancellable::NONE).map_err(|e| e.message().to_string())?;
+    let mut dirents = Vec::new();
+    while let Some(info) = enumerator.next_file(Cancellable::NONE).map_err(|e| e.message().to_string())? {
+        let name = info.display_name().to_string();
+        dirents.push(Dirent {
+            full_path: directory.child(info.name()).uri().to_string(),
+            parent_path: parent_path.clone(),
+            mime_type: info.content_type().map(|content_type| gio::content_type_get_mime_type(&content_type).unwrap_or_default().to_string()).unwrap_or_default(),
+            attributes: to_attribute(&info),
+            name,
+        });
+    }
+    Ok(dirents)
+}
+
+pub async fn stat(app: &tauri::AppHandle, uri: &str) -> Result<FileAttribute, String> {
+    mount(app, uri).await?;
+    let file = File::for_uri(uri);
+    blocking(move || file.query_info(ATTRIBUTES, FileQueryInfoFlags::NONE, Cancellable::NONE).map(|info| to_attribute(&info)).map_err(|e| e.message().to_string())).await?
+}
+
+fn to_file(path: &str) -> File {
+    if path.contains("://") {
+        File::for_uri(path)
+    } else {
+        File::for_path(path)
+    }
+}
+
+fn measure(file: &File) -> (u64, u64) {
+    match file.query_info(ATTRIBUTES, FileQueryInfoFlags::NOFOLLOW_SYMLINKS, Cancellable::NONE) {
+        Ok(info) if info.file_type() == FileType::Directory => match file.enumerate_children(ATTRIBUTES, FileQueryInfoFlags::NOFOLLOW_SYMLINKS, Cancellable::NONE) {
+            Ok(enumerator) => {
+                let mut total = (0, 0);
+                while let Ok(Some(child)) = enumerator.next_file(Cancellable::NONE) {
+                    let (count, size) = measure(&file.child(child.name()));
+                    total = (total.0 + count, total.1 + size);
+                }
+                total
+            }
+            Err(_) => (0, 0),
+        },
+        Ok(info) => (1, info.size().max(0) as u64),
+        Err(_) => (0, 0),
+    }
+}
+
+async fn copy_file(source: &File, destination: &File, flags: FileCopyFlags, cancellable: &Cancellable, progress: Box<dyn FnMut(i64, i64)>) -> Result<(), glib::Error> {
+    let (tx, rx) = smol::channel::bounded(1);
+    source.copy_async(destination, flags, glib::Priority::DEFAULT, Some(cancellable), Some(progress), move |result| {
+        let _ = tx.try_send(result);
+    });
+    rx.recv().await.unwrap_or(Ok(()))
+}
+
+// Dangling links count as existing so that they are not silently overwritten
+async fn exists(file: &File) -> bool {
+    file.query_info_future("standard::type", FileQueryInfoFlags::NOFOLLOW_SYMLINKS, glib::Priority::DEFAULT).await.is_ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Done,
+    Skipped,
+    Cancelled,
+}
+
+struct CopyContext {
+    operation: TransferOperation,
+    cancellable: Cancellable,
+    widget: crate::gtk_fs::FileOperationDialog,
+    confirm_dialog: crate::gtk_fs::FileReplaceDialog,
+    usages: Rc<RefCell<DiskUsages>>,
+    skip_or_replace: ReplaceOrSkip,
+    pause_rx: smol::channel::Receiver<bool>,
+}
+
+impl CopyContext {
+    // Moved items are deleted as soon as they are copied, so that skipped ones stay in place
+    async fn copy(&mut self, source: &File, destination: &File) -> Result<Outcome, glib::Error> {
+        if self.widget.cancelled() {
+            self.cancellable.cancel();
+            return Ok(Outcome::Cancelled);
+        }
+
+        if let Ok(pause) = self.pause_rx.try_recv() {
+            if pause {
+                let _ = self.pause_rx.recv().await;
+            }
+        }
+
+        // Only the async GIO calls are used here, since this runs on the main thread and backends may be slow
+        let info = source.query_info_future("standard::type", FileQueryInfoFlags::NOFOLLOW_SYMLINKS, glib::Priority::DEFAULT).await?;
+        if info.file_type() == FileType::Directory {
+            if !exists(destination).await {
+                destination.make_directory_future(glib::Priority::DEFAULT).await?;
+            }
+            let enumerator = source.enumerate_children_future("standard::name", FileQueryInfoFlags::NOFOLLOW_SYMLINKS, glib::Priority::DEFAULT).await?;
+            let mut outcome = Outcome::Done;
+            loop {
+                let children = enumerator.next_files_future(ENUMERATE_BATCH, glib::Priority::DEFAULT).await?;
+                if children.is_empty() {
+                    break;
+                }
+                for child in children {
+                    match Box::pin(self.copy(&source.child(child.name()), &destination.child(child.name()))).await? {
+                        Outcome::Done => {}
+                        Outcome::Skipped => outcome = Outcome::Skipped,
+                        Outcome::Cancelled => return Ok(Outcome::Cancelled),
+                    }
+                }
+            }
+            // A folder that still holds skipped items is kept
+            if outcome == Outcome::Done {
+                self.delete_moved(source).await?;
+            }
+            return Ok(outcome);
+        }
+
+        let mut flags = FileCopyFlags::NOFOLLOW_SYMLINKS | FileCopyFlags::ALL_METADATA;
+        if exists(destination).await {
+            if !self.skip_or_replace.applies_to_all() {
+                self.skip_or_replace = self.confirm_dialog.confirm(&destination.parse_name()).await;
+            }
+            match self.skip_or_replace {
+                ReplaceOrSkip::Skip | ReplaceOrSkip::SkipAll => return Ok(Outcome::Skipped),
+                ReplaceOrSkip::Cancel => return Ok(Outcome::Cancelled),
+                _ => flags |= FileCopyFlags::OVERWRITE,
+            }
+        }
+
+        self.widget.set_from_name(&source.parse_name());
+        let usages = self.usages.clone();
+        let widget = self.widget.clone();
+        let cancellable = self.cancellable.clone();
+        let operation = self.operation;
+        let base = usages.borrow().processed_size;
+        let progress = Box::new(move |current: i64, _total: i64| {
+            if widget.cancelled() {
+                cancellable.cancel();
+                return;
+            }
+            let mut usages = usages.borrow_mut();
+            usages.processed_size = base + current.max(0) as u64;
+            update_progress(&widget, &operation.into(), &mut usages);
+        });
+        copy_file(source, destination, flags, &self.cancellable, progress).await?;
+        self.delete_moved(source).await?;
+
+        let mut usages = self.usages.borrow_mut();
+        usages.processed_count += 1;
+        update_progress(&self.widget, &self.operation.into(), &mut usages);
+        Ok(Outcome::Done)
+    }
+
+    async fn delete_moved(&self, source: &File) -> Result<(), glib::Error> {
+        if self.operation == TransferOperation::Move {
+            source.delete_future(glib::Priority::DEFAULT).await?;
+        }
+        Ok(())
+    }
+}
+
+// The async move needs GIO 2.72, so the rename runs on a worker thread instead
+async fn try_rename(source: &File, target: &File) -> bool {
+    let (source, target) = (source.clone(), target.clone());
+    blocking(move || source.move_(&target, FileCopyFlags::NO_FALLBACK_FOR_MOVE | FileCopyFlags::NOFOLLOW_SYMLINKS, Cancellable::NONE, None).is_ok()).await.unwrap_or_default()
+}
+
+// Copies or moves between GIO locations with copy_async, reporting to the same dialogs as local operations
+pub fn transfer(operation: TransferOperation, froms: Vec<String>, to: String) -> Result<(), String> {
+    let (pause_tx, pause_rx) = smol::channel::bounded::<bool>(1);
+    let widget = create_progress_dialog(&operation.into(), "Preparing...", &to, pause_tx);
+
+    glib::spawn_future_local(async move {
+        let destination = to_file(&to);
+        let sources: Vec<File> = froms.iter().map(|from| to_file(from)).collect();
+
+        let mut result = ensure_mounted(&destination).await;
+        for source in &sources {
+            if result.is_ok() {
+                result = ensure_mounted(source).await;
+            }
+        }
+
+        let mut context = CopyContext {
+            operation,
+            cancellable: Cancellable::new(),
+            widget,
+            confirm_dialog: create_replace_confirm_dialog(false),
+            usages: Rc::new(RefCell::new(DiskUsages::default())),
+            skip_or_replace: ReplaceOrSkip::Replace,
+            pause_rx,
+        };
+
+        if result.is_ok() {
+            // Walking the whole tree takes long on remote backends, so it is done off the main thread
+            let measured = sources.clone();
+            let (count, size) = blocking(move || measured.iter().map(measure).fold((0, 0), |total, (count, size)| (total.0 + count, total.1 + size))).await.unwrap_or_default();
+            {
+                let mut usages = context.usages.borrow_mut();
+                usages.total_count += count;
+                usages.total_size += size;
+                update_progress(&context.widget, &operation.into(), &mut usages);
+            }
+            context.widget.show();
+
+            for source in &sources {
+                let target = destination.child(source.basename().unwrap_or_default());
+                // Renames within the same backend are instant, so try that first
+                if operation == TransferOperation::Move && try_rename(source, &target).await {
+                    continue;
+                }
+                match context.copy(source, &target).await {
+                    Ok(Outcome::Done) | Ok(Outcome::Skipped) => {}
+                    Ok(Outcome::Cancelled) => break,
+                    Err(e) => {
+                        if !e.matches(IOErrorEnum::Cancelled) {
+                            result = Err(e.message().to_string());
+                        }
+                        break;
+                    }
+                }
+            }
+        }
+
+        context.widget.close();
+
+        if let Err(e) = result {
+            zouni::dialog::message(zouni::dialog::MessageDialogOptions {
+                title: None,
+                kind: Some(zouni::dialog::MessageDialogKind::Error),
+                buttons: vec!["OK".to_string()],
+                message: e,
+                cancel_id: None,
+            })
+            .await;
+        }
+    });
+
+    Ok(())
+}
+
+pub fn is_transfer(froms: &[String], to: &str) -> bool {
+    is_uri(to) || froms.iter().any(|from| is_uri(from))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The share must be mounted beforehand, e.g. with "gio mount smb://127.0.0.1/share"
+    #[test]
+    #[ignore = "needs a mounted Samba share given in EXPLITE_TEST_SMB"]
+    fn smb_round_trip() {
+        let base = File::for_uri(&std::env::var("EXPLITE_TEST_SMB").expect("EXPLITE_TEST_SMB is not set"));
+        let local = tempfile::tempdir().unwrap();
+        let content = b"round trip";
+        std::fs::write(local.path().join("a b.txt"), content).unwrap();
+
+        glib::MainContext::default().block_on(async {
+            let directory = base.child("explite-round-trip");
+            directory.make_directory_future(glib::Priority::DEFAULT).await.unwrap();
+
+            let uploaded = directory.child("a b.txt");
+            copy_file(&File::for_path(local.path().join("a b.txt")), &uploaded, FileCopyFlags::NONE, &Cancellable::new(), Box::new(|_, _| {})).await.unwrap();
+            assert!(exists(&uploaded).await);
+
+            let listed = directory.clone();
+            let dirents = blocking(move || list(&listed)).await.unwrap().unwrap();
+            assert!(dirents.iter().any(|dirent| dirent.name == "a b.txt" && dirent.attributes.is_file && dirent.attributes.size == content.len() as u64));
+            assert_eq!(measure(&directory), (1, content.len() as u64));
+
+            let renamed = directory.child("renamed.txt");
+            assert!(try_rename(&uploaded, &renamed).await);
+            assert!(!exists(&uploaded).await);
+
+            let downloaded = local.path().join("downloaded.txt");
+            copy_file(&renamed, &File::for_path(&downloaded), FileCopyFlags::NONE, &Cancellable::new(), Box::new(|_, _| {})).await.unwrap();
+            assert_eq!(std::fs::read(&downloaded).unwrap(), content);
+
+            renamed.delete_future(glib::Priority::DEFAULT).await.unwrap();
+            directory.delete_future(glib::Priority::DEFAULT).await.unwrap();
+            assert!(!exists(&directory).await);
+        });
+    }
+}
@@ -0,0 +1,578 @@
+use crate::{
+    archive,
+    progress::DiskUsages,
+    remote::{TransferControl, TransferJob, TransferOperation, TransferStatus},
+};
+use serde::Serialize;
+use std::{
+    io::Write,
+    time::{Duration, Instant},
+};
+use zouni::fs::Response;
+
+const USAGE: &str = "Usage: explite-cli [--json] <COMMAND> [OPTIONS] [ARGS]...
+
+Commands:
+  copy [--conflict skip|replace] <SOURCE>... <DESTINATION>
+  move [--conflict skip|replace] <SOURCE>... <DESTINATION>
+  trash <PATH>...
+  restore [--to <DIRECTORY>] [--conflict skip|replace|keep-both] <ORIGINAL PATH>...
+  search <DIRECTORY> <QUERY>
+  thumbnail [--size <PIXELS>] <FILE>...
+  archive <ARCHIVE> <SOURCE>...
+  extract <ARCHIVE> <DESTINATION>
+
+Options:
+  --json          Print one JSON object per line instead of text
+  -h, --help      Print this help and exit
+  -V, --version   Print the version and exit";
+
+// Progress lines are limited so that large copies do not flood the terminal
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(200);
+// The "normal" size of the thumbnail spec
+const DEFAULT_THUMBNAIL_SIZE: u32 = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ConflictPolicy {
+    Skip,
+    Replace,
+    KeepBoth,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum Output {
+    Progress {
+        processed_count: u64,
+        total_count: u64,
+        processed_size: u64,
+        total_size: u64,
+        progress: f64,
+    },
+    Skipped {
+        path: String,
+    },
+    Found {
+        path: String,
+    },
+    Thumbnail {
+        path: String,
+        error: Option<String>,
+    },
+    Finished {
+        command: String,
+    },
+    Error {
+        message: String,
+    },
+}
+
+struct Reporter {
+    json: bool,
+    command: String,
+    last_progress: Option<Instant>,
+}
+
+impl Reporter {
+    fn emit(&mut self, output: Output) {
+        if self.json {
+            if let Ok(line) = serde_json::to_string(&output) {
+                println!("{}", line);
+            }
+            return;
+        }
+
+        match output {
+            Output::Progress {
+                processed_count,
+                total_count,
+                progress,
+                ..
+            } => {
+                eprint!("\r{} {}/{} items {}%", self.command, processed_count, total_count, (progress * 100.0).floor());
+                let _ = std::io::stderr().flush();
+            }
+            Output::Skipped {
+                path,
+            } => eprintln!("\rSkipped {}", path),
+            Output::Found {
+                path,
+            } => println!("{}", path),
+            Output::Thumbnail {
+                path,
+                error,
+            } => match error {
+                Some(e) => eprintln!("{}: {}", path, e),
+                None => println!("{}", path),
+            },
+            Output::Finished {
+                ..
+            } => {
+                if self.last_progress.is_some() {
+                    eprintln!();
+                }
+            }
+            Output::Error {
+                message,
+            } => {
+                if self.last_progress.is_some() {
+                    eprintln!();
+                }
+                eprintln!("explite-cli: {}", message);
+            }
+        }
+    }
+
+    fn progress(&mut self, usages: &mut DiskUsages, by_size: bool, force: bool) {
+        if !force && self.last_progress.is_some_and(|last| last.elapsed() < PROGRESS_INTERVAL) {
+            return;
+        }
+        self.last_progress = Some(Instant::now());
+        let progress = usages.update_progress(by_size);
+        self.emit(Output::Progress {
+            processed_count: usages.processed_count,
+            total_count: usages.total_count,
+            processed_size: usages.processed_size,
+            total_size: usages.total_size,
+            progress,
+        });
+    }
+}
+
+// Runs the command-line tool and returns the exit code
+pub fn run(args: Vec<String>) -> i32 {
+    let mut args = args.into_iter().peekable();
+    let json = args.next_if(|arg| arg == "--json").is_some();
+
+    let Some(command) = args.next() else {
+        eprintln!("{}", USAGE);
+        return 2;
+    };
+
+    let mut reporter = Reporter {
+        json,
+        command: String::new(),
+        last_progress: None,
+    };
+
+    let args: Vec<String> = args.collect();
+    let result = match command.as_str() {
+        "-h" | "--help" => {
+            println!("{}", USAGE);
+            return 0;
+        }
+        "-V" | "--version" => {
+            println!("explite-cli {}", env!("CARGO_PKG_VERSION"));
+            return 0;
+        }
+        "copy" => transfer(&mut reporter, TransferOperation::Copy, args),
+        "move" => transfer(&mut reporter, TransferOperation::Move, args),
+        "trash" => trash(&mut reporter, args),
+        "restore" => restore(&mut reporter, args),
+        "search" => search(&mut reporter, args),
+        "thumbnail" => thumbnail(&mut reporter, args),
+        "archive" => create_archive(&mut reporter, args),
+        "extract" => extract_archive(&mut reporter, args),
+        _ => Err(Failure::Error(format!("Unknown command \"{}\"", command))),
+    };
+
+    match result {
+        Ok(_) => {
+            reporter.emit(Output::Finished {
+                command,
+            });
+            0
+        }
+        Err(Failure::Usage) => {
+            eprintln!("{}", USAGE);
+            2
+        }
+        Err(Failure::Error(message)) => {
+            reporter.emit(Output::Error {
+                message,
+            });
+            1
+        }
+    }
+}
+
+enum Failure {
+    Usage,
+    Error(String),
+}
+
+impl From<String> for Failure {
+    fn from(message: String) -> Self {
+        Self::Error(message)
+    }
+}
+
+// Splits "--conflict <POLICY>" and "--to <DIRECTORY>" from the positional arguments
+fn parse_options(args: Vec<String>, allow_keep_both: bool) -> Result<(Vec<String>, ConflictPolicy, Option<String>), Failure> {
+    let mut paths = Vec::new();
+    let mut policy = ConflictPolicy::Skip;
+    let mut to = None;
+    let mut args = args.into_iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--conflict" => {
+                policy = match args.next().as_deref() {
+                    Some("skip") => ConflictPolicy::Skip,
+                    Some("replace") => ConflictPolicy::Replace,
+                    Some("keep-both") if allow_keep_both => ConflictPolicy::KeepBoth,
+                    _ => return Err(Failure::Usage),
+                };
+            }
+            "--to" => to = Some(args.next().ok_or(Failure::Usage)?),
+            _ => paths.push(arg),
+        }
+    }
+
+    Ok((paths, policy, to))
+}
+
+fn transfer(reporter: &mut Reporter, operation: TransferOperation, args: Vec<String>) -> Result<(), Failure> {
+    let (mut froms, policy, _) = parse_options(args, false)?;
+    if froms.len() < 2 {
+        return Err(Failure::Usage);
+    }
+    let to = froms.pop().unwrap_or_default();
+
+    reporter.command = match operation {
+        TransferOperation::Copy => "Copying".to_string(),
+        TransferOperation::Move => "Moving".to_string(),
+    };
+
+    let job = TransferJob::local(operation, froms, to);
+    let control = TransferControl::default();
+    let mut usages = DiskUsages::default();
+    let mut error = None;
+
+    job.run(&control, |status| {
+        usages.update(&status);
+        match status {
+            TransferStatus::Ready(_, _) | TransferStatus::End => reporter.progress(&mut usages, true, true),
+            TransferStatus::Progress(_) => reporter.progress(&mut usages, true, false),
+            TransferStatus::Confirm(target) => {
+                if policy == ConflictPolicy::Replace {
+                    return Response::Replace;
+                }
+                reporter.emit(Output::Skipped {
+                    path: target,
+                });
+                return Response::Skip;
+            }
+            TransferStatus::Error(e) => error = Some(e),
+            TransferStatus::Start(_) | TransferStatus::Finished => {}
+        }
+        Response::Proceed
+    });
+
+    match error {
+        Some(e) => Err(Failure::Error(e)),
+        None => Ok(()),
+    }
+}
+
+fn trash(reporter: &mut Reporter, paths: Vec<String>) -> Result<(), Failure> {
+    if paths.is_empty() {
+        return Err(Failure::Usage);
+    }
+    if paths.iter().any(|path| crate::remote::is_remote(path)) {
+        return Err(Failure::Error("Remote items cannot be moved to the recycle bin".to_string()));
+    }
+
+    reporter.command = "Trashing".to_string();
+    let mut usages = DiskUsages::default();
+    usages.update(&TransferStatus::Ready(paths.len() as u64, 0));
+    reporter.progress(&mut usages, false, true);
+
+    #[cfg(target_os = "windows")]
+    {
+        zouni::fs::trash_all(&paths)?;
+        usages.processed_count = usages.total_count;
+    }
+
+    #[cfg(target_os = "linux")]
+    for path in paths {
+        crate::xdg_trash::trash_file(std::path::Path::new(&path))?;
+        usages.update(&TransferStatus::End);
+        reporter.progress(&mut usages, false, false);
+    }
+
+    reporter.progress(&mut usages, false, true);
+    Ok(())
+}
+
+fn restore(reporter: &mut Reporter, args: Vec<String>) -> Result<(), Failure> {
+    let (paths, policy, to) = parse_options(args, true)?;
+    if paths.is_empty() {
+        return Err(Failure::Usage);
+    }
+
+    reporter.command = "Restoring".to_string();
+
+    #[cfg(target_os = "windows")]
+    {
+        zouni::fs::undelete(&paths)?;
+        // Restore in place first, then move the restored items into the destination
+        let Some(to) = to else {
+            return Ok(());
+        };
+        let mut args = paths;
+        args.push(to);
+        if policy == ConflictPolicy::Replace {
+            args.extend(["--conflict".to_string(), "replace".to_string()]);
+        }
+        transfer(reporter, TransferOperation::Move, args)
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        use crate::xdg_trash::{self, Conflict};
+
+        let Some(to) = to else {
+            return xdg_trash::undelete(&paths).map_err(Failure::Error);
+        };
+
+        // The latest deletion of each path is the one restored, as in the recycle bin view
+        let all_entries = xdg_trash::all_entries();
+        let entries: Vec<_> = paths
+            .iter()
+            .filter_map(|path| all_entries.iter().filter(|entry| std::path::Path::new(path) == entry.original_path).max_by(|a, b| a.deleted_date_ms.total_cmp(&b.deleted_date_ms)).cloned())
+            .collect();
+
+        let control = TransferControl::default();
+        let mut usages = DiskUsages::default();
+        let mut skipped = Vec::new();
+        xdg_trash::restore_to(
+            &entries,
+            std::path::Path::new(&to),
+            &control,
+            |status| {
+                usages.update(&status);
+                let force = !matches!(status, TransferStatus::Progress(_));
+                reporter.progress(&mut usages, true, force);
+            },
+            |target| match policy {
+                ConflictPolicy::Skip => {
+                    skipped.push(target.to_string_lossy().to_string());
+                    Conflict::Skip
+                }
+                ConflictPolicy::Replace => Conflict::Replace,
+                ConflictPolicy::KeepBoth => Conflict::KeepBoth,
+            },
+        )?;
+
+        for path in skipped {
+            reporter.emit(Output::Skipped {
+                path,
+            });
+        }
+        Ok(())
+    }
+}
+
+// Same matching as the search box, which ignores symbols and spaces
+fn strip_symbols(value: &str) -> String {
+    value.to_lowercase().chars().filter(|c| !c.is_whitespace() && !"!#$%&'()=~^-|`@{[+;]},_".contains(*c)).collect()
+}
+
+fn search(reporter: &mut Reporter, args: Vec<String>) -> Result<(), Failure> {
+    let [directory, query] = <[String; 2]>::try_from(args).map_err(|_| Failure::Usage)?;
+    let key = strip_symbols(&query);
+
+    let dirents = zouni::fs::readdir(directory, true, true)?;
+    for dirent in dirents.into_iter().filter(|dirent| !dirent.attributes.is_system) {
+        if strip_symbols(&dirent.name).contains(&key) {
+            reporter.emit(Output::Found {
+                path: dirent.full_path,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn thumbnail(reporter: &mut Reporter, args: Vec<String>) -> Result<(), Failure> {
+    let mut files = Vec::new();
+    let mut size = DEFAULT_THUMBNAIL_SIZE;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--size" {
+            size = args.next().and_then(|size| size.parse().ok()).ok_or(Failure::Usage)?;
+        } else {
+            files.push(arg);
+        }
+    }
+    if files.is_empty() {
+        return Err(Failure::Usage);
+    }
+
+    #[cfg(target_os = "linux")]
+    let thumbnail_size = crate::gtk_thumb::ThumbnailSize::nearest(size, size);
+    #[cfg(target_os = "windows")]
+    let _ = size;
+
+    let mut failed = false;
+    for file in files {
+        #[cfg(target_os = "linux")]
+        let result = {
+            let mime_type = zouni::fs::get_mime_type(&file);
+            // There is no fallback decoder here, so failures are recorded right away
+            if mime_type.starts_with("video/") {
+                crate::gtk_thumb::video_thumbnail(&file, thumbnail_size, Some("explite")).inspect_err(|_| crate::gtk_thumb::record_failure(&file, Some("explite")))
+            } else if mime_type.starts_with("image/") {
+                crate::gtk_thumb::image_thumbnail(&file, thumbnail_size, Some("explite")).inspect_err(|_| crate::gtk_thumb::record_failure(&file, Some("explite")))
+            } else {
+                crate::gtk_thumb::external_thumbnail(&file, &mime_type, thumbnail_size, Some("explite"))
+            }
+        };
+        #[cfg(target_os = "windows")]
+        let result: Result<Vec<u8>, String> = Err("Thumbnails are stored in the freedesktop cache, which is only available on Linux".to_string());
+
+        let error = result.err();
+        failed |= error.is_some();
+        reporter.emit(Output::Thumbnail {
+            path: file,
+            error,
+        });
+    }
+
+    if failed {
+        Err(Failure::Error("Some thumbnails could not be created".to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+// The format follows the archive suffix and is limited to what the system tar supports
+fn create_archive(reporter: &mut Reporter, args: Vec<String>) -> Result<(), Failure> {
+    let Some((archive, sources)) = args.split_first().filter(|(_, sources)| !sources.is_empty()) else {
+        return Err(Failure::Usage);
+    };
+
+    reporter.command = "Archiving".to_string();
+    let mut usages = DiskUsages::default();
+    usages.update(&TransferStatus::Ready(sources.len() as u64, 0));
+    reporter.progress(&mut usages, false, true);
+
+    archive::create(archive, sources)?;
+
+    usages.processed_count = usages.total_count;
+    reporter.progress(&mut usages, false, true);
+    Ok(())
+}
+
+fn extract_archive(reporter: &mut Reporter, args: Vec<String>) -> Result<(), Failure> {
+    let [archive, destination] = <[String; 2]>::try_from(args).map_err(|_| Failure::Usage)?;
+
+    reporter.command = "Extracting".to_string();
+    let mut usages = DiskUsages::default();
+    usages.update(&TransferStatus::Ready(1, 0));
+    reporter.progress(&mut usages, false, true);
+
+    archive::extract(&archive, &destination)?;
+
+    usages.update(&TransferStatus::End);
+    reporter.progress(&mut usages, false, true);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::Path};
+
+    fn reporter() -> Reporter {
+        Reporter {
+            json: true,
+            command: String::new(),
+            last_progress: None,
+        }
+    }
+
+    fn path(path: &Path) -> String {
+        path.to_string_lossy().to_string()
+    }
+
+    #[test]
+    fn move_keeps_skipped_sources() {
+        let source = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        fs::write(source.path().join("a.txt"), "new").unwrap();
+        fs::write(source.path().join("b.txt"), "new").unwrap();
+        fs::write(destination.path().join("a.txt"), "old").unwrap();
+
+        let args = vec![path(&source.path().join("a.txt")), path(&source.path().join("b.txt")), path(destination.path())];
+        assert!(transfer(&mut reporter(), TransferOperation::Move, args).is_ok());
+
+        assert_eq!(fs::read_to_string(source.path().join("a.txt")).unwrap(), "new");
+        assert_eq!(fs::read_to_string(destination.path().join("a.txt")).unwrap(), "old");
+        assert!(!source.path().join("b.txt").exists());
+        assert_eq!(fs::read_to_string(destination.path().join("b.txt")).unwrap(), "new");
+    }
+
+    #[test]
+    fn move_keeps_folders_holding_skipped_items() {
+        let source = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let folder = source.path().join("folder");
+        fs::create_dir(&folder).unwrap();
+        fs::write(folder.join("a.txt"), "new").unwrap();
+        fs::write(folder.join("b.txt"), "new").unwrap();
+        fs::create_dir(destination.path().join("folder")).unwrap();
+        fs::write(destination.path().join("folder").join("a.txt"), "old").unwrap();
+
+        let args = vec![path(&folder), path(destination.path())];
+        assert!(transfer(&mut reporter(), TransferOperation::Move, args).is_ok());
+
+        assert_eq!(fs::read_to_string(folder.join("a.txt")).unwrap(), "new");
+        assert!(!folder.join("b.txt").exists());
+        assert_eq!(fs::read_to_string(destination.path().join("folder").join("a.txt")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(destination.path().join("folder").join("b.txt")).unwrap(), "new");
+    }
+
+    #[test]
+    fn move_replaces_when_asked() {
+        let source = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        fs::write(source.path().join("a.txt"), "new").unwrap();
+        fs::write(destination.path().join("a.txt"), "old").unwrap();
+
+        let args = vec!["--conflict".to_string(), "replace".to_string(), path(&source.path().join("a.txt")), path(destination.path())];
+        assert!(transfer(&mut reporter(), TransferOperation::Move, args).is_ok());
+
+        assert!(!source.path().join("a.txt").exists());
+        assert_eq!(fs::read_to_string(destination.path().join("a.txt")).unwrap(), "new");
+    }
+
+    #[test]
+    fn archive_round_trip() {
+        let source = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let folder = source.path().join("folder");
+        fs::create_dir(&folder).unwrap();
+        fs::write(folder.join("a b.txt"), "content").unwrap();
+        let archive = path(&source.path().join("archive.tar.gz"));
+
+        assert!(create_archive(&mut reporter(), vec![archive.clone(), path(&folder)]).is_ok());
+        assert!(extract_archive(&mut reporter(), vec![archive, path(destination.path())]).is_ok());
+
+        assert_eq!(fs::read_to_string(destination.path().join("folder").join("a b.txt")).unwrap(), "content");
+    }
+
+    #[test]
+    fn archive_names_are_not_options() {
+        let source = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let file = source.path().join("--checkpoint=1");
+        fs::write(&file, "content").unwrap();
+        let archive = path(&source.path().join("archive.tar"));
+
+        assert!(create_archive(&mut reporter(), vec![archive.clone(), path(&file)]).is_ok());
+        assert!(extract_archive(&mut reporter(), vec![archive, path(destination.path())]).is_ok());
+
+        assert_eq!(fs::read_to_string(destination.path().join("--checkpoint=1")).unwrap(), "content");
+    }
+}
@@ -1,30 +1,106 @@
 use crate::{
-    session::Session,
+    restore::WindowBounds,
+    session::{OpenRequest, Session},
+    tabs::WindowTabs,
     watcher::{self, WatchTx},
     IconInfo, ThumbnailArgs,
 };
-use std::{collections::HashMap, path::PathBuf};
-use tauri::Manager;
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+use tauri::{AppHandle, Manager, WebviewWindow, WebviewWindowBuilder};
 use zouni::{process::SpawnOption, Size};
 
+pub const MAIN_WINDOW: &str = "View";
+static WINDOW_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Default)]
+pub struct WindowInit {
+    pub urls: Vec<String>,
+    pub options: HashMap<String, String>,
+    pub tabs: Option<WindowTabs>,
+    pub bounds: Option<WindowBounds>,
+}
+
+// Launch arguments keyed by window label
+pub struct WindowArgs(pub Mutex<HashMap<String, WindowInit>>);
+
 pub fn setup(app: &tauri::App) {
-    let mut urls = Vec::new();
-    for arg in std::env::args().skip(1) {
-        urls.push(arg);
-    }
-    app.manage(urls);
+    let args = crate::cli::handle(app);
 
     let id = &app.config().identifier;
-    if let Ok(session) = crate::session::start(id) {
-        app.manage(session);
+    match crate::session::start(app.app_handle(), id) {
+        Ok(session) => {
+            app.manage(session);
+        }
+        Err(_) => {
+            let request = OpenRequest {
+                paths: args.paths.clone(),
+                options: args.options.clone(),
+            };
+            if crate::session::forward(id, &request).is_ok() {
+                std::process::exit(0);
+            }
+        }
     }
+    app.manage(WindowArgs(Mutex::new(HashMap::from([(
+        MAIN_WINDOW.to_string(),
+        WindowInit {
+            urls: args.paths,
+            options: args.options,
+            ..Default::default()
+        },
+    )]))));
+
+    crate::menu::setup(app);
+    crate::tabs::setup(app);
+    crate::thumbnail::setup(app);
+    // Only the primary instance keeps the session
+    if app.try_state::<Session>().is_some() {
+        crate::restore::setup(app);
+    }
+    crate::remote::setup(app);
+    #[cfg(target_os = "linux")]
+    crate::trash_policy::setup(app);
+    #[cfg(target_os = "linux")]
+    crate::thumbnail_cache::setup(app);
 
     let (tx_cmd, rx_cmd) = smol::channel::bounded(5);
     app.manage(WatchTx(tx_cmd));
     watcher::spwan_watcher(app.app_handle(), rx_cmd).unwrap();
 }
 
+// Additional windows share the configuration of the main window
+pub fn create_window(app: &AppHandle, mut init: WindowInit) -> Result<WebviewWindow, String> {
+    let mut config = app.config().app.windows.iter().find(|config| config.label == MAIN_WINDOW).cloned().ok_or("Window config not found")?;
+    config.label = format!("{}-{}", MAIN_WINDOW, WINDOW_COUNT.fetch_add(1, Ordering::Relaxed) + 1);
+    // Moved or restored tabs keep their history
+    if let Some(tabs) = init.tabs.take() {
+        crate::tabs::restore(app, &config.label, tabs);
+    }
+    app.state::<WindowArgs>().0.lock().unwrap().insert(config.label.clone(), init);
+    WebviewWindowBuilder::from_config(app, &config).map_err(|e| e.to_string())?.build().map_err(|e| e.to_string())
+}
+
+pub fn close_window(app: &AppHandle, label: &str) {
+    crate::watcher::forget_window(app, label);
+    crate::menu::remove(app, label);
+    crate::tabs::forget_window(app, label);
+    crate::thumbnail::forget_window(app, label);
+    app.state::<WindowArgs>().0.lock().unwrap().remove(label);
+
+    if app.webview_windows().keys().all(|window_label| window_label == label) {
+        exit(app);
+    }
+}
+
 pub fn exit(app: &tauri::AppHandle) {
+    let _ = crate::restore::save(app, true);
     if let Some(session) = app.try_state::<Session>() {
         crate::session::end(session.inner());
     }
@@ -4,15 +4,36 @@ use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, env, path::PathBuf};
 use tauri::{AppHandle, Emitter, Manager, WebviewWindow};
 use zouni::dialog::MessageResult;
+mod archive;
+mod cli;
 mod dialog;
 #[cfg(target_os = "linux")]
 mod gtk_thumb;
+#[cfg(target_os = "linux")]
+mod gtk_vfs;
+mod headless;
 mod helper;
 mod menu;
+mod mount;
+mod progress;
+mod remote;
+mod restore;
 mod session;
+mod sftp;
+mod tabs;
+mod thumbnail;
+#[cfg(target_os = "linux")]
+mod thumbnail_cache;
+#[cfg(target_os = "linux")]
+mod thumbnailer;
 mod translate;
 mod watcher;
-use watcher::WatchTx;
+mod webdav;
+#[cfg(target_os = "linux")]
+mod trash_policy;
+#[cfg(target_os = "linux")]
+mod xdg_trash;
+use watcher::{WatchRequest, WatchTx};
 #[cfg(target_os = "linux")]
 mod gtk_fs;
 
@@ -65,9 +86,28 @@ struct ReadDirRequest {
     recursive: bool,
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+enum Dirents {
+    Local(Vec<zouni::Dirent>),
+    Remote(Vec<remote::Dirent>),
+}
+
+// Remote calls wait on the network, so they run on a blocking thread instead of an async worker
+async fn blocking<T: Send + 'static>(task: impl FnOnce() -> T + Send + 'static) -> Result<T, String> {
+    tauri::async_runtime::spawn_blocking(task).await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
-fn readdir(payload: ReadDirRequest) -> Vec<zouni::Dirent> {
-    zouni::fs::readdir(payload.directory, payload.recursive, true).unwrap_or_default()
+async fn readdir(app: AppHandle, payload: ReadDirRequest) -> Result<Dirents, String> {
+    if remote::is_remote(&payload.directory) {
+        return blocking(move || remote::readdir(&app, &payload.directory)).await?.map(Dirents::Remote);
+    }
+    #[cfg(target_os = "linux")]
+    if gtk_vfs::is_uri(&payload.directory) {
+        return gtk_vfs::readdir(&app, &payload.directory).await.map(Dirents::Remote);
+    }
+    Ok(Dirents::Local(zouni::fs::readdir(payload.directory, payload.recursive, true).unwrap_or_default()))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -76,7 +116,10 @@ struct RenameInfo {
     old: String,
 }
 #[tauri::command]
-fn rename(payload: RenameInfo) -> Result<(), String> {
+async fn rename(app: AppHandle, payload: RenameInfo) -> Result<(), String> {
+    if remote::is_remote(&payload.old) {
+        return blocking(move || remote::rename(&app, &payload.old, &payload.new)).await?;
+    }
     std::fs::rename(payload.old, payload.new).map_err(|e| e.to_string())
 }
 
@@ -90,9 +133,23 @@ fn start_drag(payload: Vec<String>) -> Result<(), String> {
     zouni::drag_drop::start_drag(payload, zouni::Operation::Copy)
 }
 
+#[derive(Debug, Clone, Serialize)]
+#[serde(untagged)]
+enum Attribute {
+    Local(zouni::FileAttribute),
+    Remote(remote::FileAttribute),
+}
+
 #[tauri::command]
-fn stat(payload: String) -> Result<zouni::FileAttribute, String> {
-    zouni::fs::stat(&payload)
+async fn stat(app: AppHandle, payload: String) -> Result<Attribute, String> {
+    if remote::is_remote(&payload) {
+        return blocking(move || remote::stat(&app, &payload)).await?.map(Attribute::Remote);
+    }
+    #[cfg(target_os = "linux")]
+    if gtk_vfs::is_uri(&payload) {
+        return gtk_vfs::stat(&app, &payload).await.map(Attribute::Remote);
+    }
+    zouni::fs::stat(&payload).map(Attribute::Local)
 }
 
 #[tauri::command]
@@ -100,43 +157,185 @@ fn get_mime_type(payload: String) -> String {
     zouni::fs::get_mime_type(payload)
 }
 
+#[allow(unused_variables)]
 #[tauri::command]
-fn trash(payload: Vec<String>) -> Result<(), String> {
+async fn trash(app: AppHandle, payload: Vec<String>) -> Result<(), String> {
+    if payload.iter().any(|path| remote::is_remote(path)) {
+        return Err("Remote items cannot be moved to the recycle bin".to_string());
+    }
     #[cfg(target_os = "windows")]
     {
         zouni::fs::trash_all(&payload)
     }
     #[cfg(target_os = "linux")]
     {
-        gtk_fs::trash(payload)
+        // Measuring folders and copying across devices can take long, so keep it off the async runtime
+        tauri::async_runtime::spawn_blocking(move || xdg_trash::trash(payload)).await.map_err(|e| e.to_string())??;
+        trash_policy::run_in_background(&app);
+        Ok(())
     }
 }
 
+// GTK dialogs and shell file operations are started from the main thread, where sync commands used to run
+async fn on_main_thread<T: Send + 'static>(app: &AppHandle, task: impl FnOnce() -> T + Send + 'static) -> Result<T, String> {
+    let (tx, rx) = smol::channel::bounded(1);
+    app.run_on_main_thread(move || {
+        let _ = tx.send_blocking(task());
+    })
+    .map_err(|e| e.to_string())?;
+    rx.recv().await.map_err(|e| e.to_string())
+}
+
 #[tauri::command]
-fn delete(payload: Vec<String>) -> Result<(), String> {
+async fn delete(app: AppHandle, payload: Vec<String>) -> Result<(), String> {
+    if payload.iter().any(|path| remote::is_remote(path)) {
+        return blocking(move || remote::delete(&app, &payload)).await?;
+    }
+    on_main_thread(&app, move || {
+        #[cfg(target_os = "windows")]
+        {
+            zouni::fs::delete_all(&payload)
+        }
+        #[cfg(target_os = "linux")]
+        {
+            gtk_fs::delete(payload)
+        }
+    })
+    .await?
+}
+
+#[tauri::command]
+fn undelete(payload: Vec<String>) -> Result<(), String> {
     #[cfg(target_os = "windows")]
     {
-        zouni::fs::delete_all(&payload)
+        zouni::fs::undelete(&payload)
     }
     #[cfg(target_os = "linux")]
     {
-        gtk_fs::delete(payload)
+        xdg_trash::undelete(&payload)
     }
 }
 
+#[cfg(target_os = "windows")]
+type RecycleBinItem = zouni::RecycleBinItem;
+#[cfg(target_os = "linux")]
+type RecycleBinItem = xdg_trash::RecycleBinItem;
+
 #[tauri::command]
-fn undelete(payload: Vec<String>) -> Result<(), String> {
-    zouni::fs::undelete(&payload)
+fn undelete_by_time(payload: Vec<RecycleBinItem>) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        zouni::fs::undelete_by_time(&payload)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        xdg_trash::undelete_by_time(&payload)
+    }
 }
 
 #[tauri::command]
-fn undelete_by_time(payload: Vec<zouni::RecycleBinItem>) -> Result<(), String> {
-    zouni::fs::undelete_by_time(&payload)
+fn delete_from_recycle_bin(payload: Vec<RecycleBinItem>) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        zouni::fs::delete_from_recycle_bin(&payload)
+    }
+    #[cfg(target_os = "linux")]
+    {
+        xdg_trash::delete_from_recycle_bin(&payload)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RestoreInfo {
+    items: Vec<RecycleBinItem>,
+    destination: String,
+}
+
+#[cfg(target_os = "windows")]
+const CONFLICT_BUTTONS: [&str; 3] = ["Replace", "Skip", "Keep both"];
+
+// Finds a name free in both folders, e.g. "file (2).txt", so the item can be renamed in place before the move
+#[cfg(target_os = "windows")]
+fn keep_both_name(destination: &std::path::Path, original: &std::path::Path) -> String {
+    let stem = original.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = original.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+    (2..).map(|index| format!("{} ({}){}", stem, index, extension)).find(|name| !destination.join(name).exists() && !original.with_file_name(name).exists()).unwrap()
+}
+
+// Conflicts are resolved before anything is undeleted, then the shell moves the items with its own progress dialog
+#[cfg(target_os = "windows")]
+async fn restore_into(items: Vec<RecycleBinItem>, destination: String) -> Result<(), String> {
+    let destination = std::path::Path::new(&destination);
+    let mut restores = Vec::new();
+    let mut replaced = Vec::new();
+    let mut froms = Vec::new();
+    let mut renames = Vec::new();
+
+    for item in items {
+        let original = std::path::PathBuf::from(&item.original_path);
+        let name = original.file_name().ok_or(format!("Cannot restore {}", item.original_path))?;
+        let target = destination.join(name);
+        let mut from = original.clone();
+        if target.exists() {
+            let result = zouni::dialog::message(zouni::dialog::MessageDialogOptions {
+                title: None,
+                kind: Some(zouni::dialog::MessageDialogKind::Warning),
+                buttons: CONFLICT_BUTTONS.iter().map(|button| button.to_string()).collect(),
+                message: format!("{} already exists", target.display()),
+                cancel_id: None,
+            })
+            .await;
+            match CONFLICT_BUTTONS.iter().position(|button| *button == result.button) {
+                Some(0) => replaced.push(target),
+                Some(1) => continue,
+                Some(2) => {
+                    from = original.with_file_name(keep_both_name(destination, &original));
+                    renames.push((original, from.clone()));
+                }
+                _ => return Ok(()),
+            }
+        }
+        froms.push(from);
+        restores.push(item);
+    }
+
+    if restores.is_empty() {
+        return Ok(());
+    }
+
+    zouni::fs::undelete_by_time(&restores)?;
+    for (original, renamed) in &renames {
+        std::fs::rename(original, renamed).map_err(|e| e.to_string())?;
+    }
+    if !replaced.is_empty() {
+        zouni::fs::delete_all(&replaced)?;
+    }
+    zouni::fs::mv_all(&froms, destination)
 }
 
 #[tauri::command]
-fn delete_from_recycle_bin(payload: Vec<zouni::RecycleBinItem>) -> Result<(), String> {
-    zouni::fs::delete_from_recycle_bin(&payload)
+async fn restore_to(app: AppHandle, payload: RestoreInfo) -> Result<(), String> {
+    #[cfg(target_os = "windows")]
+    {
+        tauri::async_runtime::spawn(async move {
+            if let Err(e) = restore_into(payload.items, payload.destination).await {
+                zouni::dialog::message(zouni::dialog::MessageDialogOptions {
+                    title: None,
+                    kind: Some(zouni::dialog::MessageDialogKind::Error),
+                    buttons: vec!["OK".to_string()],
+                    message: e,
+                    cancel_id: None,
+                })
+                .await;
+            }
+        });
+        Ok(())
+    }
+    #[cfg(target_os = "linux")]
+    {
+        let entries = blocking(move || xdg_trash::find(&payload.items)).await?;
+        on_main_thread(&app, move || gtk_fs::restore_to(entries, payload.destination)).await?
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -145,28 +344,61 @@ struct CopyInfo {
     to: String,
 }
 
-#[tauri::command]
-fn copy(payload: CopyInfo) -> Result<(), String> {
+async fn transfer(window: &WebviewWindow, operation: remote::TransferOperation, payload: CopyInfo) -> Result<(), String> {
+    let app = window.app_handle().clone();
+    let job = blocking(move || remote::TransferJob::new(&app, operation, payload.from, payload.to)).await??;
     #[cfg(target_os = "windows")]
     {
-        zouni::fs::copy_all(&payload.from, payload.to)
+        remote::transfer(window.app_handle(), window.label(), job)
     }
     #[cfg(target_os = "linux")]
     {
-        gtk_fs::copy(payload)
+        on_main_thread(window.app_handle(), move || gtk_fs::transfer(job)).await?
     }
 }
 
 #[tauri::command]
-fn mv(payload: CopyInfo) -> Result<(), String> {
-    #[cfg(target_os = "windows")]
-    {
-        zouni::fs::mv_all(&payload.from, payload.to)
+async fn copy(window: WebviewWindow, payload: CopyInfo) -> Result<(), String> {
+    if remote::is_transfer(&payload.from, &payload.to) {
+        return transfer(&window, remote::TransferOperation::Copy, payload).await;
     }
-    #[cfg(target_os = "linux")]
-    {
-        gtk_fs::mv(payload)
+    on_main_thread(window.app_handle(), move || {
+        #[cfg(target_os = "linux")]
+        if gtk_vfs::is_transfer(&payload.from, &payload.to) {
+            return gtk_vfs::transfer(remote::TransferOperation::Copy, payload.from, payload.to);
+        }
+        #[cfg(target_os = "windows")]
+        {
+            zouni::fs::copy_all(&payload.from, payload.to)
+        }
+        #[cfg(target_os = "linux")]
+        {
+            gtk_fs::copy(payload)
+        }
+    })
+    .await?
+}
+
+#[tauri::command]
+async fn mv(window: WebviewWindow, payload: CopyInfo) -> Result<(), String> {
+    if remote::is_transfer(&payload.from, &payload.to) {
+        return transfer(&window, remote::TransferOperation::Move, payload).await;
     }
+    on_main_thread(window.app_handle(), move || {
+        #[cfg(target_os = "linux")]
+        if gtk_vfs::is_transfer(&payload.from, &payload.to) {
+            return gtk_vfs::transfer(remote::TransferOperation::Move, payload.from, payload.to);
+        }
+        #[cfg(target_os = "windows")]
+        {
+            zouni::fs::mv_all(&payload.from, payload.to)
+        }
+        #[cfg(target_os = "linux")]
+        {
+            gtk_fs::mv(payload)
+        }
+    })
+    .await?
 }
 
 #[tauri::command]
@@ -202,7 +434,10 @@ fn write_text(window: WebviewWindow, payload: String) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn mkdir(payload: String) -> Result<(), String> {
+async fn mkdir(app: AppHandle, payload: String) -> Result<(), String> {
+    if remote::is_remote(&payload) {
+        return blocking(move || remote::mkdir(&app, &payload)).await?;
+    }
     std::fs::create_dir(payload).map_err(|e| e.to_string())
 }
 
@@ -246,7 +481,7 @@ struct ColumnWithLabel {
 #[tauri::command]
 fn prepare_menu(window: WebviewWindow, payload: Vec<ColumnWithLabel>) {
     let window_handle = get_window_handel(&window);
-    menu::create(window.app_handle(), window_handle, payload);
+    menu::create(window.app_handle(), window.label(), window_handle, payload);
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -268,7 +503,7 @@ fn change_theme(window: WebviewWindow, payload: String) {
         _ => (tauri::Theme::Light, wcpopup::config::Theme::System),
     };
     let _ = window.set_theme(Some(tauri_them));
-    menu::change_menu_theme(window.app_handle(), menu_theme);
+    menu::change_menu_theme(window.app_handle(), window.label(), menu_theme);
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -323,6 +558,46 @@ async fn open_column_context_menu(window: WebviewWindow, payload: ColumnMenuArg)
     menu::open_column_context_menu(window.app_handle(), window.label(), payload.position, payload.items, payload.is_recycle_bin).await;
 }
 
+#[tauri::command]
+async fn open_tab_context_menu(window: WebviewWindow, payload: menu::Position) {
+    menu::popup_menu(window.app_handle(), window.label(), menu::TAB, payload, None, false).await;
+}
+
+#[tauri::command]
+fn list_tabs(window: WebviewWindow) -> tabs::WindowTabs {
+    tabs::list(window.app_handle(), window.label())
+}
+
+#[tauri::command]
+fn open_tab(window: WebviewWindow, payload: tabs::Tab) -> tabs::Tab {
+    tabs::open(window.app_handle(), window.label(), payload)
+}
+
+#[tauri::command]
+fn update_tab(window: WebviewWindow, payload: tabs::Tab) {
+    tabs::update(window.app_handle(), window.label(), payload);
+}
+
+#[tauri::command]
+fn activate_tab(window: WebviewWindow, payload: u32) -> Option<tabs::Tab> {
+    tabs::activate(window.app_handle(), window.label(), payload)
+}
+
+#[tauri::command]
+fn close_tab(window: WebviewWindow, payload: u32) -> Option<tabs::Tab> {
+    tabs::close(window.app_handle(), window.label(), payload)
+}
+
+#[tauri::command]
+fn reopen_closed_tab(window: WebviewWindow) -> Option<tabs::Tab> {
+    tabs::reopen(window.app_handle(), window.label())
+}
+
+#[tauri::command]
+async fn detach_tab(window: WebviewWindow, payload: u32) -> Result<Option<tabs::Tab>, String> {
+    tabs::detach(window.app_handle(), window.label(), payload)
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct OpenFileFolderOption {
     title: String,
@@ -348,24 +623,28 @@ fn create_symlink(payload: SymlinkRequest) -> Result<(), String> {
     zouni::fs::create_symlink(payload.path, payload.link_path)
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct NotifyRequest {
-    path: String,
-    network: bool,
+#[tauri::command]
+async fn watch(window: WebviewWindow, payload: WatchRequest) -> Result<(), String> {
+    if let Some(tx) = window.app_handle().try_state::<WatchTx>() {
+        tx.inner().0.send(WatcherCommand::Watch(window.label().to_string(), payload)).await.map_err(|e| e.to_string())
+    } else {
+        Ok(())
+    }
 }
+
 #[tauri::command]
-async fn watch(app: AppHandle, payload: NotifyRequest) -> Result<(), String> {
-    if let Some(tx) = app.try_state::<WatchTx>() {
-        tx.inner().0.send(WatcherCommand::Watch(payload.path, payload.network)).await.map_err(|e| e.to_string())
+async fn unwatch(window: WebviewWindow, payload: WatchRequest) -> Result<(), String> {
+    if let Some(tx) = window.app_handle().try_state::<WatchTx>() {
+        tx.inner().0.send(WatcherCommand::Unwatch(window.label().to_string(), payload)).await.map_err(|e| e.to_string())
     } else {
         Ok(())
     }
 }
 
 #[tauri::command]
-async fn unwatch(app: AppHandle, payload: NotifyRequest) -> Result<(), String> {
+async fn set_poll_intervals(app: AppHandle, payload: HashMap<String, u64>) -> Result<(), String> {
     if let Some(tx) = app.try_state::<WatchTx>() {
-        tx.inner().0.send(WatcherCommand::Unwatch(payload.path, payload.network)).await.map_err(|e| e.to_string())
+        tx.inner().0.send(WatcherCommand::SetPollIntervals(payload)).await.map_err(|e| e.to_string())
     } else {
         Ok(())
     }
@@ -381,6 +660,31 @@ struct TerminalArgs {
     path: String,
     admin: bool,
 }
+#[tauri::command]
+fn set_connection_profiles(app: AppHandle, payload: Vec<remote::ConnectionProfile>) {
+    remote::set_profiles(&app, payload);
+}
+
+#[tauri::command]
+fn disconnect(app: AppHandle, payload: String) {
+    remote::disconnect(&app, &payload);
+}
+
+#[tauri::command]
+async fn unknown_host_key(app: AppHandle, payload: String) -> Result<Option<String>, String> {
+    blocking(move || remote::unknown_host_key(&app, &payload)).await?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct TrustHostKeyRequest {
+    uri: String,
+    fingerprint: String,
+}
+#[tauri::command]
+async fn trust_host_key(app: AppHandle, payload: TrustHostKeyRequest) -> Result<(), String> {
+    blocking(move || remote::trust_host_key(&app, &payload.uri, &payload.fingerprint)).await?
+}
+
 #[tauri::command]
 fn open_terminal(payload: TerminalArgs) -> Result<(), String> {
     if cfg!(windows) {
@@ -397,51 +701,69 @@ fn open_terminal(payload: TerminalArgs) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn launch_new(app: AppHandle) -> Result<(), String> {
-    let app_path = tauri::process::current_binary(&app.env()).map_err(|e| e.to_string())?;
-
-    if cfg!(windows) {
-        zouni::shell::open_path(app_path)
-    } else {
-        std::process::Command::new(app_path).spawn().map_err(|e| e.to_string())?;
-        Ok(())
-    }
+async fn launch_new(app: AppHandle) -> Result<(), String> {
+    helper::create_window(&app, Default::default())?;
+    Ok(())
 }
 
 #[tauri::command]
-fn open_in_new_window(app: AppHandle, payload: String) -> Result<(), String> {
-    let app_path = tauri::process::current_binary(&app.env()).map_err(|e| e.to_string())?;
-    if cfg!(windows) {
-        zouni::shell::open_path_with(payload, app_path)
-    } else {
-        std::process::Command::new(app_path).arg(payload).spawn().map_err(|e| e.to_string())?;
-        Ok(())
-    }
+async fn open_in_new_window(app: AppHandle, payload: String) -> Result<(), String> {
+    helper::create_window(
+        &app,
+        helper::WindowInit {
+            urls: vec![payload],
+            ..Default::default()
+        },
+    )?;
+    Ok(())
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct InitArgs {
     urls: Vec<String>,
+    options: HashMap<String, String>,
     locales: Vec<String>,
     restore_position: bool,
+    bounds: Option<restore::WindowBounds>,
+    restorable: Option<restore::Restorable>,
 }
 #[tauri::command]
-fn get_args(app: AppHandle) -> InitArgs {
-    if let Some(urls) = app.try_state::<Vec<String>>() {
+fn get_args(window: WebviewWindow) -> InitArgs {
+    let app = window.app_handle();
+    // Only the main window of the primary instance restores its saved position
+    let restore_position = window.label() == helper::MAIN_WINDOW && app.try_state::<Session>().is_some();
+    if let Some(init) = app.state::<helper::WindowArgs>().0.lock().unwrap().get(window.label()) {
+        // The previous session is offered only when nothing was requested on the command line
+        let restorable = if restore_position && init.urls.is_empty() && init.options.is_empty() {
+            restore::restorable(app)
+        } else {
+            None
+        };
         return InitArgs {
-            urls: urls.inner().clone(),
+            urls: init.urls.clone(),
+            options: init.options.clone(),
             locales: vec![zouni::shell::get_locale()],
-            restore_position: app.try_state::<Session>().is_some(),
+            restore_position,
+            bounds: init.bounds.clone(),
+            restorable,
         };
     }
 
     InitArgs {
         urls: Vec::new(),
+        options: HashMap::new(),
         locales: Vec::new(),
-        restore_position: app.try_state::<Session>().is_some(),
+        restore_position,
+        bounds: None,
+        restorable: None,
     }
 }
 
+#[tauri::command]
+async fn restore_session(app: AppHandle, payload: bool) -> Result<Option<restore::WindowBounds>, String> {
+    restore::restore(&app, payload)
+}
+
 #[allow(unused_variables)]
 #[tauri::command]
 fn register_drop_target(window: WebviewWindow) -> Result<(), String> {
@@ -456,15 +778,18 @@ fn register_drop_target(window: WebviewWindow) -> Result<(), String> {
 }
 
 #[tauri::command]
-fn listen_devices(window: WebviewWindow) -> bool {
+fn listen_devices(app: AppHandle) -> bool {
     zouni::device::listen(move |event| {
-        window.emit("device_event", event).unwrap();
+        app.emit("device_event", event).unwrap();
     })
 }
 
+// Device events are shared by all windows
 #[tauri::command]
-fn unlisten_devices() {
-    zouni::device::unlisten();
+fn unlisten_devices(window: WebviewWindow) {
+    if window.app_handle().webview_windows().len() <= 1 {
+        zouni::device::unlisten();
+    }
 }
 
 #[allow(unused_variables)]
@@ -492,54 +817,114 @@ fn unlisten_file_drop() {
     zouni::webview2::clear();
 }
 
-#[tauri::command]
-fn read_recycle_bin() -> Result<Vec<zouni::RecycleBinDirent>, String> {
-    zouni::fs::read_recycle_bin()
-}
-
-#[tauri::command]
-fn empty_recycle_bin() -> Result<(), String> {
-    zouni::fs::empty_recycle_bin(None)
-}
+#[cfg(target_os = "windows")]
+type RecycleBinDirent = zouni::RecycleBinDirent;
+#[cfg(target_os = "linux")]
+type RecycleBinDirent = xdg_trash::RecycleBinDirent;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct ThumbnailArgs {
-    full_path: String,
-    width: u32,
-    height: u32,
-}
 #[tauri::command]
-async fn to_thumbnail(payload: ThumbnailArgs) -> Result<Vec<u8>, String> {
+fn read_recycle_bin() -> Result<Vec<RecycleBinDirent>, String> {
     #[cfg(target_os = "windows")]
     {
-        helper::video_thumbnail(payload).await.map_err(|e| e.to_string())
+        zouni::fs::read_recycle_bin()
     }
     #[cfg(target_os = "linux")]
     {
-        if let Ok(data) = helper::video_thumbnail(payload.clone()).await {
-            Ok(data)
-        } else {
-            Ok(gtk_thumb::video_thumbnail(payload.full_path, Some("explite"))?.0)
-        }
+        xdg_trash::read_recycle_bin()
     }
 }
 
 #[tauri::command]
-async fn to_image_thumbnail(payload: String) -> Result<Vec<u8>, String> {
+fn empty_recycle_bin(payload: Option<String>) -> Result<(), String> {
     #[cfg(target_os = "windows")]
     {
-        helper::image_thumbnail(payload).await.map_err(|e| e.to_string())
+        zouni::fs::empty_recycle_bin(payload)
     }
     #[cfg(target_os = "linux")]
     {
-        if let Ok(data) = helper::image_thumbnail(payload.clone()).await {
-            Ok(data)
+        if let Some(mount_point) = payload {
+            trash_policy::empty_volume(&mount_point)
         } else {
-            Ok(gtk_thumb::image_thumbnail(payload, Some("explite"))?.0)
+            xdg_trash::empty_recycle_bin()
         }
     }
 }
 
+#[cfg(target_os = "linux")]
+#[tauri::command]
+fn set_file_manager_service(app: AppHandle, payload: bool) -> Result<(), String> {
+    if let Some(session) = app.try_state::<Session>() {
+        session::claim_file_manager(session.inner(), payload)?;
+    }
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[tauri::command]
+fn set_trash_policy(app: AppHandle, payload: trash_policy::TrashPolicy) {
+    trash_policy::set_policy(&app, payload);
+}
+
+#[cfg(target_os = "linux")]
+#[tauri::command]
+async fn find_oversized_items(app: AppHandle, payload: Vec<String>) -> Result<Vec<String>, String> {
+    blocking(move || trash_policy::oversized(&app, &payload)).await
+}
+
+#[cfg(target_os = "linux")]
+#[tauri::command]
+async fn trash_usage() -> Vec<trash_policy::TrashUsage> {
+    trash_policy::usage()
+}
+
+#[cfg(target_os = "linux")]
+#[tauri::command]
+fn set_thumbnail_cache_policy(app: AppHandle, payload: thumbnail_cache::ThumbnailCachePolicy) {
+    thumbnail_cache::set_policy(&app, payload);
+}
+
+#[cfg(target_os = "linux")]
+#[tauri::command]
+async fn clean_thumbnail_cache(payload: thumbnail_cache::ThumbnailCachePolicy) -> Result<thumbnail_cache::CleanResult, String> {
+    tauri::async_runtime::spawn_blocking(move || thumbnail_cache::clean(&payload)).await.map_err(|e| e.to_string())?
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct ThumbnailArgs {
+    full_path: String,
+    width: u32,
+    height: u32,
+}
+
+#[tauri::command]
+fn request_thumbnails(window: WebviewWindow, payload: Vec<thumbnail::ThumbnailRequest>) {
+    thumbnail::request(window.app_handle(), window.label(), payload);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PrefetchThumbnailsArgs {
+    directory: String,
+    size: u32,
+}
+
+#[tauri::command]
+async fn prefetch_thumbnails(window: WebviewWindow, payload: PrefetchThumbnailsArgs) -> Result<Vec<thumbnail::CachedThumbnail>, String> {
+    // Reading the directory and checking the cache of every file blocks, so it runs off the async runtime
+    let app = window.app_handle().clone();
+    let label = window.label().to_string();
+    tauri::async_runtime::spawn_blocking(move || thumbnail::prefetch(&app, &label, payload.directory, payload.size)).await.map_err(|e| e.to_string())?
+}
+
+#[tauri::command]
+fn thumbnailer_mime_types() -> Vec<String> {
+    thumbnail::thumbnailer_mime_types()
+}
+
+#[tauri::command]
+fn cancel_thumbnails(window: WebviewWindow, payload: Vec<String>) {
+    thumbnail::cancel(window.app_handle(), window.label(), payload);
+}
+
 #[tauri::command]
 fn is_file(payload: String) -> bool {
     PathBuf::from(payload).is_file()
@@ -583,6 +968,11 @@ fn redo(window: WebviewWindow) -> Result<(), String> {
         .map_err(|e| e.to_string())
 }
 
+// Entry point of explite-cli, which runs the file operations without windows
+pub fn run_cli() -> i32 {
+    headless::run(std::env::args().skip(1).collect())
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -591,10 +981,19 @@ pub fn run() {
             helper::setup(app);
             Ok(())
         })
-        .on_window_event(|window, event| {
-            if let tauri::WindowEvent::Destroyed = event {
-                helper::exit(window.app_handle());
+        .on_window_event(|window, event| match event {
+            tauri::WindowEvent::CloseRequested {
+                ..
+            } => {
+                // The last window is snapshotted before it goes away
+                if window.app_handle().webview_windows().keys().all(|label| label == window.label()) {
+                    restore::remember(window.app_handle());
+                }
             }
+            tauri::WindowEvent::Destroyed => {
+                helper::close_window(window.app_handle(), window.label());
+            }
+            _ => {}
         })
         .invoke_handler(tauri::generate_handler![
             prepare_menu,
@@ -602,6 +1001,15 @@ pub fn run() {
             open_fav_context_menu,
             open_recycle_context_menu,
             open_column_context_menu,
+            open_tab_context_menu,
+            list_tabs,
+            restore_session,
+            open_tab,
+            update_tab,
+            activate_tab,
+            close_tab,
+            reopen_closed_tab,
+            detach_tab,
             exists,
             open_path,
             open_path_with,
@@ -617,6 +1025,7 @@ pub fn run() {
             delete,
             undelete,
             undelete_by_time,
+            restore_to,
             copy,
             mv,
             is_uris_available,
@@ -631,6 +1040,7 @@ pub fn run() {
             write_text_file,
             watch,
             unwatch,
+            set_poll_intervals,
             open_terminal,
             message,
             launch_new,
@@ -648,11 +1058,29 @@ pub fn run() {
             read_recycle_bin,
             empty_recycle_bin,
             delete_from_recycle_bin,
-            to_thumbnail,
-            to_image_thumbnail,
+            request_thumbnails,
+            cancel_thumbnails,
+            prefetch_thumbnails,
+            thumbnailer_mime_types,
             is_file,
             assoc_icons,
             get_wsl_names,
+            set_connection_profiles,
+            disconnect,
+            unknown_host_key,
+            trust_host_key,
+            #[cfg(target_os = "linux")]
+            set_file_manager_service,
+            #[cfg(target_os = "linux")]
+            set_trash_policy,
+            #[cfg(target_os = "linux")]
+            find_oversized_items,
+            #[cfg(target_os = "linux")]
+            trash_usage,
+            #[cfg(target_os = "linux")]
+            set_thumbnail_cache_policy,
+            #[cfg(target_os = "linux")]
+            clean_thumbnail_cache,
             #[cfg(target_os = "linux")]
             undo,
             #[cfg(target_os = "linux")]
@@ -1,7 +1,7 @@
 use crate::translate::t;
 use crate::{AppMenuItem, Column, ColumnWithLabel};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::Path};
+use std::{collections::HashMap, path::Path, sync::Arc};
 use tauri::async_runtime::Mutex;
 use tauri::{Emitter, EventTarget, Manager};
 use wcpopup::config::{IconSettings, MenuFont};
@@ -16,6 +16,7 @@ pub const FAV: &str = "fav";
 pub const NO_ITEM: &str = "noitem";
 pub const RECYCLE_BIN: &str = "recyclebin";
 pub const COLUMN: &str = "column";
+pub const TAB: &str = "tab";
 const MENU_EVENT_NAME: &str = "contextmenu_event";
 const TARGET_FILE: &str = "File";
 const TARGET_FOLDER: &str = "Folder";
@@ -31,6 +32,13 @@ const CHECK_MARK: &str = r#"
     </svg>
 "#;
 
+// Menu events are routed to the tab that was active when the menu opened
+#[derive(Debug, Clone, Serialize)]
+struct MenuEvent {
+    id: String,
+    tab: u32,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Position {
     x: i32,
@@ -38,19 +46,44 @@ pub struct Position {
 }
 
 pub struct Menus(HashMap<String, Menu>);
-type MenusState = Mutex<Menus>;
+type WindowMenus = Arc<Mutex<Menus>>;
+// Menus are owned by the window they pop up on
+pub struct MenusState(std::sync::Mutex<HashMap<String, WindowMenus>>);
 pub struct AppMenuItems(Vec<AppMenuItem>);
 type AppMenuItemsState = Mutex<AppMenuItems>;
 
-pub fn create(app_handle: &tauri::AppHandle, window_handle: isize, columns: Vec<ColumnWithLabel>) {
-    let list = create_list_menu(window_handle);
+pub fn setup(app: &tauri::App) {
+    app.manage(MenusState(std::sync::Mutex::new(HashMap::new())));
+    app.manage(Mutex::new(AppMenuItems(Vec::new())));
+}
+
+pub fn create(app_handle: &tauri::AppHandle, window_label: &str, window_handle: isize, columns: Vec<ColumnWithLabel>) {
+    let mut list = create_list_menu(window_handle);
+    let app_item_state = app_handle.state::<AppMenuItemsState>();
+    let items = app_item_state.try_lock().unwrap();
+    insert_app_items(&mut list, &items.0);
     let fav = create_fav_menu(window_handle);
     let no_item = create_noitem_menu(window_handle);
     let recycle_bin = create_recycle_bin_menu(window_handle);
     let column_menu = create_column_menu(window_handle, columns);
-    let menus = Menus(HashMap::from([(LIST.to_string(), list), (FAV.to_string(), fav), (NO_ITEM.to_string(), no_item), (RECYCLE_BIN.to_string(), recycle_bin), (COLUMN.to_string(), column_menu)]));
-    app_handle.manage(Mutex::new(menus));
-    app_handle.manage(Mutex::new(AppMenuItems(Vec::new())));
+    let tab = create_tab_menu(window_handle);
+    let menus = Menus(HashMap::from([
+        (LIST.to_string(), list),
+        (FAV.to_string(), fav),
+        (NO_ITEM.to_string(), no_item),
+        (RECYCLE_BIN.to_string(), recycle_bin),
+        (COLUMN.to_string(), column_menu),
+        (TAB.to_string(), tab),
+    ]));
+    app_handle.state::<MenusState>().0.lock().unwrap().insert(window_label.to_string(), Arc::new(Mutex::new(menus)));
+}
+
+pub fn remove(app_handle: &tauri::AppHandle, window_label: &str) {
+    app_handle.state::<MenusState>().0.lock().unwrap().remove(window_label);
+}
+
+fn window_menus(app_handle: &tauri::AppHandle, window_label: &str) -> Option<WindowMenus> {
+    app_handle.state::<MenusState>().0.lock().unwrap().get(window_label).cloned()
 }
 
 #[allow(unused_variables)]
@@ -61,7 +94,9 @@ pub async fn popup_menu(app_handle: &tauri::AppHandle, window_label: &str, menu_
     } else {
         menu_name
     };
-    let state = app_handle.state::<MenusState>();
+    let Some(state) = window_menus(app_handle, window_label) else {
+        return;
+    };
     let menus = state.try_lock().unwrap();
     let menu = menus.0.get(target_menu_name).unwrap();
 
@@ -75,27 +110,18 @@ pub async fn popup_menu(app_handle: &tauri::AppHandle, window_label: &str, menu_
         terminal.set_visible(show_admin_runas);
     }
 
+    let tab = crate::tabs::active(app_handle, window_label);
     let result = menu.popup_at_async(position.x, position.y).await;
 
     if let Some(item) = result {
-        app_handle
-            .emit_to(
-                EventTarget::WebviewWindow {
-                    label: window_label.to_string(),
-                },
-                MENU_EVENT_NAME,
-                if item.id.starts_with(APP_MENU_ITEM_PREFIX) {
-                    item.id.replace(APP_MENU_ITEM_PREFIX, "")
-                } else {
-                    item.id
-                },
-            )
-            .unwrap();
+        emit_menu_event(app_handle, window_label, item, tab);
     };
 }
 
 pub async fn open_column_context_menu(app_handle: &tauri::AppHandle, window_label: &str, position: Position, items: Vec<Column>, is_recycle_bin: bool) {
-    let state = app_handle.state::<MenusState>();
+    let Some(state) = window_menus(app_handle, window_label) else {
+        return;
+    };
     let menus = state.try_lock().unwrap();
     let menu = menus.0.get(COLUMN).unwrap();
 
@@ -113,27 +139,38 @@ pub async fn open_column_context_menu(app_handle: &tauri::AppHandle, window_labe
         }
     }
 
+    let tab = crate::tabs::active(app_handle, window_label);
     let result = menu.popup_at_async(position.x, position.y).await;
 
     if let Some(item) = result {
-        app_handle
-            .emit_to(
-                EventTarget::WebviewWindow {
-                    label: window_label.to_string(),
-                },
-                MENU_EVENT_NAME,
-                if item.id.starts_with(APP_MENU_ITEM_PREFIX) {
-                    item.id.replace(APP_MENU_ITEM_PREFIX, "")
-                } else {
-                    item.id
-                },
-            )
-            .unwrap();
+        emit_menu_event(app_handle, window_label, item, tab);
+    };
+}
+
+fn emit_menu_event(app_handle: &tauri::AppHandle, window_label: &str, item: MenuItem, tab: u32) {
+    let id = if item.id.starts_with(APP_MENU_ITEM_PREFIX) {
+        item.id.replace(APP_MENU_ITEM_PREFIX, "")
+    } else {
+        item.id
     };
+    app_handle
+        .emit_to(
+            EventTarget::WebviewWindow {
+                label: window_label.to_string(),
+            },
+            MENU_EVENT_NAME,
+            MenuEvent {
+                id,
+                tab,
+            },
+        )
+        .unwrap();
 }
 
-pub fn change_menu_theme(app_handle: &tauri::AppHandle, theme: Theme) {
-    let state = app_handle.state::<MenusState>();
+pub fn change_menu_theme(app_handle: &tauri::AppHandle, window_label: &str, theme: Theme) {
+    let Some(state) = window_menus(app_handle, window_label) else {
+        return;
+    };
     let menus = state.try_lock().unwrap();
 
     for menu in menus.0.values() {
@@ -160,6 +197,7 @@ fn update_open_with(menu: &Menu, file_path: &str) {
     if Path::new(file_path).is_dir() {
         select_app_item.set_visible(false);
         submenu.insert(MenuItem::builder(MenuItemType::Text).id("OpenInNewWindow").label(t!("OpenInNewWindow")).build(), 0);
+        submenu.insert(MenuItem::builder(MenuItemType::Text).id("OpenInNewTab").label(t!("OpenInNewTab")).build(), 0);
         return;
     }
 
@@ -229,22 +267,29 @@ fn toggle_app_items(app_handle: &tauri::AppHandle, menu: &Menu, file_path: &str)
 }
 
 pub fn change_app_menu_items(app_handle: &tauri::AppHandle, new_app_menu_items: Vec<AppMenuItem>) {
-    let state = app_handle.state::<MenusState>();
-    let mut menus = state.try_lock().unwrap();
-    let menu = menus.0.get_mut(LIST).unwrap();
-
     let app_item_state = app_handle.state::<AppMenuItemsState>();
     let mut items = app_item_state.try_lock().unwrap();
 
-    for old_item in &items.0 {
-        if let Some(item) = menu.get_menu_item_by_id(&old_item.path) {
-            #[cfg(target_os = "windows")]
-            menu.remove_at(item.index as _);
-            #[cfg(target_os = "linux")]
-            menu.remove(&item);
+    let windows: Vec<WindowMenus> = app_handle.state::<MenusState>().0.lock().unwrap().values().cloned().collect();
+    for state in windows {
+        let mut menus = state.try_lock().unwrap();
+        let menu = menus.0.get_mut(LIST).unwrap();
+
+        for old_item in &items.0 {
+            if let Some(item) = menu.get_menu_item_by_id(&old_item.path) {
+                #[cfg(target_os = "windows")]
+                menu.remove_at(item.index as _);
+                #[cfg(target_os = "linux")]
+                menu.remove(&item);
+            }
         }
+
+        insert_app_items(menu, &new_app_menu_items);
     }
+    items.0 = new_app_menu_items;
+}
 
+fn insert_app_items(menu: &mut Menu, new_app_menu_items: &[AppMenuItem]) {
     let terminal = menu.get_menu_item_by_id("Terminal").unwrap();
 
     #[cfg(target_os = "windows")]
@@ -272,7 +317,6 @@ pub fn change_app_menu_items(app_handle: &tauri::AppHandle, new_app_menu_items:
             menu.insert(item, start_index + i as u32);
         }
     }
-    items.0 = new_app_menu_items;
 }
 
 fn app_menu_item_id(app_path: &str) -> String {
@@ -348,6 +392,7 @@ fn create_recycle_bin_menu(window_handle: isize) -> Menu {
     let config = get_menu_config(Theme::System);
     let mut builder = MenuBuilder::new_from_config(window_handle, config);
     builder.text("Undelete", t!("Undelete"), false);
+    builder.text("RestoreTo", t!("RestoreTo"), false);
     builder.separator();
     builder.text("DeleteFromRecycleBin", t!("DeleteFromRecycleBin"), false);
     builder.text("EmptyRecycleBin", t!("EmptyRecycleBin"), false);
@@ -377,3 +422,15 @@ fn create_column_menu(window_handle: isize, columns: Vec<ColumnWithLabel>) -> Me
 
     builder.build().unwrap()
 }
+
+fn create_tab_menu(window_handle: isize) -> Menu {
+    let config = get_menu_config(Theme::System);
+    let mut builder = MenuBuilder::new_from_config(window_handle, config);
+    builder.text("DuplicateTab", t!("DuplicateTab"), false);
+    builder.text("MoveTabToNewWindow", t!("MoveTabToNewWindow"), false);
+    builder.separator();
+    builder.text_with_accelerator("CloseTab", t!("CloseTab"), false, "Ctrl+W");
+    builder.text_with_accelerator("ReopenClosedTab", t!("ReopenClosedTab"), false, "Ctrl+Shift+T");
+
+    builder.build().unwrap()
+}
@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(target_os = "linux")]
+const REMOTE_FS_TYPES: [&str; 16] =
+    ["nfs", "nfs4", "cifs", "smb3", "smbfs", "9p", "afs", "ceph", "glusterfs", "davfs", "fuse.sshfs", "fuse.rclone", "fuse.s3fs", "fuse.gvfsd-fuse", "fuse.davfs2", "fuse.glusterfs"];
+
+// statfs magic numbers of network filesystems
+#[cfg(target_os = "linux")]
+const REMOTE_FS_MAGICS: [i64; 9] = [
+    0x6969,     // NFS
+    0x517B,     // SMB
+    0xFF534D42, // CIFS
+    0xFE534D42, // SMB2
+    0x73757245, // CODA
+    0x5346414F, // AFS
+    0x01021997, // 9P
+    0x00C36400, // CEPH
+    0x564C,     // NCP
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountInfo {
+    pub mount_point: PathBuf,
+    pub remote: bool,
+}
+
+#[cfg(target_os = "linux")]
+struct MountEntry {
+    mount_point: PathBuf,
+    fs_type: String,
+}
+
+// Fields are separated from the filesystem type by a single "-"
+#[cfg(target_os = "linux")]
+fn mount_entries() -> Vec<MountEntry> {
+    let mountinfo = std::fs::read_to_string("/proc/self/mountinfo").unwrap_or_default();
+    mountinfo
+        .lines()
+        .filter_map(|line| {
+            let (mount, filesystem) = line.split_once(" - ")?;
+            Some(MountEntry {
+                mount_point: PathBuf::from(crate::xdg_trash::unescape_mount_path(mount.split_whitespace().nth(4)?)),
+                fs_type: filesystem.split_whitespace().next()?.to_string(),
+            })
+        })
+        .collect()
+}
+
+#[cfg(target_os = "linux")]
+fn statfs_type(path: &Path) -> Option<i64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } == 0 {
+        Some(stat.f_type as i64)
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub fn detect(path: &Path) -> MountInfo {
+    // The innermost mount is the one with the longest mount point
+    let entry = mount_entries().into_iter().filter(|entry| path.starts_with(&entry.mount_point)).max_by_key(|entry| entry.mount_point.as_os_str().len());
+
+    let (mount_point, fs_type) = match entry {
+        Some(entry) => (entry.mount_point, entry.fs_type),
+        None => (PathBuf::from("/"), String::new()),
+    };
+
+    let remote = if REMOTE_FS_TYPES.contains(&fs_type.as_str()) {
+        true
+    } else if fs_type == "fuse" || fs_type == "fuseblk" || fs_type.starts_with("fuse.") {
+        // FUSE shares one magic number, so only the network types listed above count as remote
+        false
+    } else {
+        statfs_type(path).map(|magic| REMOTE_FS_MAGICS.contains(&magic)).unwrap_or_default()
+    };
+
+    MountInfo {
+        mount_point,
+        remote,
+    }
+}
+
+#[cfg(target_os = "windows")]
+pub fn detect(path: &Path) -> MountInfo {
+    let full_path = path.to_string_lossy();
+    // UNC paths including \\wsl.localhost are served over the network redirector
+    if let Some(unc) = full_path.strip_prefix(r"\\") {
+        let share: Vec<&str> = unc.split('\\').take(2).collect();
+        return MountInfo {
+            mount_point: PathBuf::from(format!(r"\\{}", share.join(r"\"))),
+            remote: true,
+        };
+    }
+
+    MountInfo {
+        mount_point: path.components().next().map(|component| PathBuf::from(component.as_os_str())).unwrap_or_default(),
+        remote: false,
+    }
+}
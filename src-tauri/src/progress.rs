@@ -0,0 +1,40 @@
+use crate::remote::TransferStatus;
+
+// Progress of a file operation, shared by the dialogs and the command-line tool
+#[derive(Default, Debug, Copy, Clone)]
+pub struct DiskUsages {
+    pub total_size: u64,
+    pub total_count: u64,
+    pub processed_count: u64,
+    pub processed_size: u64,
+    pub progress: f64,
+}
+
+impl DiskUsages {
+    pub fn update(&mut self, status: &TransferStatus) {
+        match status {
+            TransferStatus::Ready(total_count, total_size) => {
+                self.total_count = *total_count;
+                self.total_size = *total_size;
+            }
+            TransferStatus::Progress(size) => self.processed_size += size,
+            TransferStatus::End => self.processed_count += 1,
+            _ => {}
+        }
+    }
+
+    // Copies and moves are measured by size when it is known, everything else by item count
+    pub fn update_progress(&mut self, by_size: bool) -> f64 {
+        let (current, total) = if by_size && self.total_size != 0 {
+            (self.processed_size, self.total_size)
+        } else {
+            (self.processed_count, self.total_count)
+        };
+        self.progress = if total == 0 {
+            0.0
+        } else {
+            current as f64 / total as f64
+        };
+        self.progress
+    }
+}
@@ -0,0 +1,775 @@
+use crate::{
+    sftp::{self, SftpProvider},
+    webdav::WebDavProvider,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, UNIX_EPOCH},
+};
+use tauri::Manager;
+use zouni::fs::Response;
+
+pub const SFTP_SCHEME: &str = "sftp://";
+pub const DAV_SCHEME: &str = "dav://";
+pub const DAVS_SCHEME: &str = "davs://";
+#[cfg(target_os = "windows")]
+const TRANSFER_EVENT_NAME: &str = "transfer_event";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum Auth {
+    Agent,
+    Key {
+        private_key: String,
+        passphrase: Option<String>,
+    },
+    Basic {
+        password: String,
+    },
+    Token {
+        token: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConnectionProfile {
+    pub id: String,
+    pub name: String,
+    pub scheme: String,
+    pub host: String,
+    pub port: Option<u16>,
+    pub user: String,
+    pub auth: Auth,
+    pub root: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileAttribute {
+    pub is_device: bool,
+    pub is_directory: bool,
+    pub is_file: bool,
+    pub is_hidden: bool,
+    pub is_read_only: bool,
+    pub is_symbolic_link: bool,
+    pub is_system: bool,
+    pub atime_ms: f64,
+    pub ctime_ms: f64,
+    pub mtime_ms: f64,
+    pub birthtime_ms: f64,
+    pub size: u64,
+    pub link_path: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Dirent {
+    pub name: String,
+    pub parent_path: String,
+    pub full_path: String,
+    pub mime_type: String,
+    pub attributes: FileAttribute,
+}
+
+pub trait Provider: Send + Sync {
+    fn readdir(&self, directory: &str) -> Result<Vec<(String, FileAttribute)>, String>;
+    fn stat(&self, path: &str) -> Result<FileAttribute, String>;
+    fn mkdir(&self, path: &str) -> Result<(), String>;
+    fn rename(&self, from: &str, to: &str) -> Result<(), String>;
+    fn remove(&self, path: &str) -> Result<(), String>;
+    fn open(&self, path: &str) -> Result<Box<dyn Read + Send>, String>;
+    fn upload(&self, path: &str, reader: &mut dyn Read, size: u64) -> Result<(), String>;
+
+    // Server side copy. Returns false when the provider cannot copy without streaming the data
+    fn copy(&self, _from: &str, _to: &str) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    // Creates a symbolic link in place of any existing item. Returns false when the provider cannot create links
+    fn symlink(&self, _target: &str, _path: &str) -> Result<bool, String> {
+        Ok(false)
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        self.stat(path).is_ok()
+    }
+
+    // Whether the session behind the provider has died, e.g. after a server restart or idle timeout
+    fn is_closed(&self) -> bool {
+        false
+    }
+}
+
+pub struct LocalProvider;
+
+impl Provider for LocalProvider {
+    fn readdir(&self, directory: &str) -> Result<Vec<(String, FileAttribute)>, String> {
+        let mut entries = Vec::new();
+        for entry in std::fs::read_dir(directory).map_err(|e| e.to_string())?.flatten() {
+            let name = entry.file_name().to_string_lossy().to_string();
+            let attribute = self.stat(&entry.path().to_string_lossy())?;
+            entries.push((name, attribute));
+        }
+        Ok(entries)
+    }
+
+    fn stat(&self, path: &str) -> Result<FileAttribute, String> {
+        let metadata = std::fs::symlink_metadata(path).map_err(|e| e.to_string())?;
+        let to_ms = |time: std::io::Result<std::time::SystemTime>| time.ok().and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_millis() as f64).unwrap_or_default();
+        Ok(FileAttribute {
+            is_directory: metadata.is_dir(),
+            is_file: metadata.is_file(),
+            is_symbolic_link: metadata.is_symlink(),
+            is_read_only: metadata.permissions().readonly(),
+            atime_ms: to_ms(metadata.accessed()),
+            mtime_ms: to_ms(metadata.modified()),
+            ctime_ms: to_ms(metadata.modified()),
+            birthtime_ms: to_ms(metadata.created()),
+            size: metadata.len(),
+            link_path: if metadata.is_symlink() {
+                std::fs::read_link(path).map(|link| link.to_string_lossy().to_string()).unwrap_or_default()
+            } else {
+                String::new()
+            },
+            ..Default::default()
+        })
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), String> {
+        std::fs::create_dir(path).map_err(|e| e.to_string())
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), String> {
+        std::fs::rename(from, to).map_err(|e| e.to_string())
+    }
+
+    fn remove(&self, path: &str) -> Result<(), String> {
+        if std::fs::symlink_metadata(path).is_ok_and(|metadata| metadata.is_dir()) {
+            std::fs::remove_dir_all(path).map_err(|e| e.to_string())
+        } else {
+            std::fs::remove_file(path).map_err(|e| e.to_string())
+        }
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn Read + Send>, String> {
+        Ok(Box::new(std::fs::File::open(path).map_err(|e| e.to_string())?))
+    }
+
+    fn upload(&self, path: &str, reader: &mut dyn Read, _size: u64) -> Result<(), String> {
+        let mut file = std::fs::File::create(path).map_err(|e| e.to_string())?;
+        std::io::copy(reader, &mut file).map_err(|e| e.to_string())?;
+        file.flush().map_err(|e| e.to_string())
+    }
+
+    fn symlink(&self, target: &str, path: &str) -> Result<bool, String> {
+        if std::fs::symlink_metadata(path).is_ok() {
+            self.remove(path)?;
+        }
+        #[cfg(target_os = "windows")]
+        {
+            let is_dir = Path::new(path).parent().is_some_and(|parent| parent.join(target).is_dir());
+            if is_dir {
+                std::os::windows::fs::symlink_dir(target, path).map_err(|e| e.to_string())?;
+            } else {
+                std::os::windows::fs::symlink_file(target, path).map_err(|e| e.to_string())?;
+            }
+        }
+        #[cfg(target_os = "linux")]
+        std::os::unix::fs::symlink(target, path).map_err(|e| e.to_string())?;
+        Ok(true)
+    }
+}
+
+#[derive(Default)]
+pub struct Connections {
+    profiles: Mutex<HashMap<String, ConnectionProfile>>,
+    providers: Mutex<HashMap<String, Arc<dyn Provider>>>,
+}
+
+pub fn setup(app: &tauri::App) {
+    app.manage(Connections::default());
+}
+
+pub fn set_profiles(app: &tauri::AppHandle, profiles: Vec<ConnectionProfile>) {
+    let connections = app.state::<Connections>();
+    let mut providers = connections.providers.lock().unwrap();
+    let mut current = connections.profiles.lock().unwrap();
+    // Drop sessions whose profile was removed or edited
+    providers.retain(|id, _| profiles.iter().any(|profile| &profile.id == id && current.get(id) == Some(profile)));
+    *current = profiles.into_iter().map(|profile| (profile.id.clone(), profile)).collect();
+}
+
+pub fn disconnect(app: &tauri::AppHandle, id: &str) {
+    app.state::<Connections>().providers.lock().unwrap().remove(id);
+}
+
+pub fn is_remote(path: &str) -> bool {
+    path.starts_with(SFTP_SCHEME) || path.starts_with(DAV_SCHEME) || path.starts_with(DAVS_SCHEME)
+}
+
+// Splits "scheme://<profile id>/path" into the profile id and the remote path
+pub fn split(uri: &str) -> Option<(&str, String)> {
+    let rest = uri.split_once("://")?.1;
+    match rest.split_once('/') {
+        Some((id, path)) => Some((id, format!("/{}", path))),
+        None => Some((rest, "/".to_string())),
+    }
+}
+
+fn join(parent: &str, name: &str) -> String {
+    if parent.ends_with('/') {
+        format!("{parent}{name}")
+    } else {
+        format!("{parent}/{name}")
+    }
+}
+
+fn file_name(path: &str) -> String {
+    path.trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string()
+}
+
+fn connect(app: &tauri::AppHandle, id: &str) -> Result<Arc<dyn Provider>, String> {
+    let connections = app.state::<Connections>();
+    if let Some(provider) = connections.providers.lock().unwrap().get(id) {
+        return Ok(provider.clone());
+    }
+
+    let profile = connections.profiles.lock().unwrap().get(id).cloned().ok_or(format!("Connection {} not found", id))?;
+    let provider: Arc<dyn Provider> = match profile.scheme.as_str() {
+        "sftp" => Arc::new(SftpProvider::connect(&profile)?),
+        "dav" | "davs" => Arc::new(WebDavProvider::connect(&profile)?),
+        _ => return Err(format!("Unsupported scheme {}", profile.scheme)),
+    };
+    connections.providers.lock().unwrap().insert(id.to_string(), provider.clone());
+    Ok(provider)
+}
+
+// Drops the cached provider when its session has died so the next call connects again
+fn evict_closed(app: &tauri::AppHandle, id: &str, provider: &Arc<dyn Provider>) -> bool {
+    if !provider.is_closed() {
+        return false;
+    }
+    let connections = app.state::<Connections>();
+    let mut providers = connections.providers.lock().unwrap();
+    if providers.get(id).is_some_and(|cached| Arc::ptr_eq(cached, provider)) {
+        providers.remove(id);
+    }
+    true
+}
+
+// Runs the operation with the provider of the path, reconnecting once when the cached session has died
+fn with_provider<T>(app: &tauri::AppHandle, path: &str, operation: impl Fn(&dyn Provider, &str) -> Result<T, String>) -> Result<T, String> {
+    if !is_remote(path) {
+        return operation(&LocalProvider, path);
+    }
+    let (id, remote_path) = split(path).ok_or(format!("Invalid path {}", path))?;
+    let provider = connect(app, id)?;
+    match operation(provider.as_ref(), &remote_path) {
+        Err(_) if evict_closed(app, id, &provider) => operation(connect(app, id)?.as_ref(), &remote_path),
+        result => result,
+    }
+}
+
+fn sftp_profile(app: &tauri::AppHandle, uri: &str) -> Result<Option<ConnectionProfile>, String> {
+    if !uri.starts_with(SFTP_SCHEME) {
+        return Ok(None);
+    }
+    let (id, _) = split(uri).ok_or(format!("Invalid path {}", uri))?;
+    let profile = app.state::<Connections>().profiles.lock().unwrap().get(id).cloned().ok_or(format!("Connection {} not found", id))?;
+    Ok(Some(profile))
+}
+
+// Returns the fingerprint to confirm when the SFTP host of the uri is not in known_hosts yet
+pub fn unknown_host_key(app: &tauri::AppHandle, uri: &str) -> Result<Option<String>, String> {
+    match sftp_profile(app, uri)? {
+        Some(profile) => sftp::unknown_host_key(&profile),
+        None => Ok(None),
+    }
+}
+
+pub fn trust_host_key(app: &tauri::AppHandle, uri: &str, fingerprint: &str) -> Result<(), String> {
+    match sftp_profile(app, uri)? {
+        Some(profile) => sftp::trust_host_key(&profile, fingerprint),
+        None => Ok(()),
+    }
+}
+
+// Resolves any path, local or remote, into its provider and the path the provider understands
+pub fn resolve(app: &tauri::AppHandle, path: &str) -> Result<(Arc<dyn Provider>, String), String> {
+    if !is_remote(path) {
+        return Ok((Arc::new(LocalProvider), path.to_string()));
+    }
+    let (id, remote_path) = split(path).ok_or(format!("Invalid path {}", path))?;
+    Ok((connect(app, id)?, remote_path))
+}
+
+// Transfers keep their providers for the whole job, so a dead session is replaced before it starts
+fn resolve_live(app: &tauri::AppHandle, path: &str) -> Result<(Arc<dyn Provider>, String), String> {
+    let (provider, remote_path) = resolve(app, path)?;
+    match split(path) {
+        Some((id, _)) if is_remote(path) && evict_closed(app, id, &provider) => Ok((connect(app, id)?, remote_path)),
+        _ => Ok((provider, remote_path)),
+    }
+}
+
+pub fn readdir(app: &tauri::AppHandle, directory: &str) -> Result<Vec<Dirent>, String> {
+    let parent_path = directory.trim_end_matches('/').to_string();
+    let entries = with_provider(app, directory, |provider, path| provider.readdir(path))?;
+    Ok(entries
+        .into_iter()
+        .map(|(name, attributes)| Dirent {
+            full_path: join(&parent_path, &name),
+            parent_path: parent_path.clone(),
+            mime_type: if attributes.is_directory {
+                String::new()
+            } else {
+                zouni::fs::get_mime_type(&name)
+            },
+            attributes: FileAttribute {
+                is_hidden: name.starts_with('.'),
+                ..attributes
+            },
+            name,
+        })
+        .collect())
+}
+
+pub fn stat(app: &tauri::AppHandle, path: &str) -> Result<FileAttribute, String> {
+    with_provider(app, path, |provider, path| provider.stat(path))
+}
+
+pub fn mkdir(app: &tauri::AppHandle, path: &str) -> Result<(), String> {
+    with_provider(app, path, |provider, path| provider.mkdir(path))
+}
+
+// "scheme://<profile id>" of a remote path, which identifies the connection it lives on
+fn connection_of(uri: &str) -> Option<(&str, &str)> {
+    let (scheme, _) = uri.split_once("://")?;
+    Some((scheme, split(uri)?.0))
+}
+
+pub fn rename(app: &tauri::AppHandle, from: &str, to: &str) -> Result<(), String> {
+    if !is_remote(to) || connection_of(from) != connection_of(to) {
+        return Err("Items cannot be renamed to another connection".to_string());
+    }
+    let (_, to) = split(to).ok_or(format!("Invalid path {}", to))?;
+    with_provider(app, from, |provider, from| provider.rename(from, &to))
+}
+
+pub fn delete(app: &tauri::AppHandle, paths: &[String]) -> Result<(), String> {
+    for path in paths {
+        with_provider(app, path, |provider, path| provider.remove(path))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TransferOperation {
+    Copy,
+    Move,
+}
+
+impl From<TransferOperation> for zouni::fs::FileOperation {
+    fn from(operation: TransferOperation) -> Self {
+        match operation {
+            TransferOperation::Copy => zouni::fs::FileOperation::Copy,
+            TransferOperation::Move => zouni::fs::FileOperation::Move,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum TransferStatus {
+    Ready(u64, u64),
+    Start(String),
+    Progress(u64),
+    Confirm(String),
+    End,
+    Finished,
+    Error(String),
+}
+
+#[derive(Default)]
+pub struct TransferControl {
+    cancelled: AtomicBool,
+    paused: AtomicBool,
+}
+
+impl TransferControl {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub(crate) fn wait(&self) -> bool {
+        while self.paused.load(Ordering::Relaxed) && !self.cancelled.load(Ordering::Relaxed) {
+            std::thread::sleep(Duration::from_millis(100));
+        }
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Outcome {
+    Done,
+    Skipped,
+    Cancelled,
+}
+
+pub struct TransferJob {
+    pub operation: TransferOperation,
+    pub froms: Vec<String>,
+    pub to: String,
+    source: Vec<(Arc<dyn Provider>, String)>,
+    destination: (Arc<dyn Provider>, String),
+}
+
+struct ProgressReader<'a, R, F> {
+    inner: R,
+    control: &'a TransferControl,
+    on_status: &'a mut F,
+}
+
+impl<R: Read, F: FnMut(TransferStatus) -> Response> Read for ProgressReader<'_, R, F> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        // Interrupted would make io::copy retry forever
+        if self.control.wait() {
+            return Err(std::io::Error::other("Cancelled"));
+        }
+        let size = self.inner.read(buf)?;
+        (self.on_status)(TransferStatus::Progress(size as u64));
+        Ok(size)
+    }
+}
+
+impl TransferJob {
+    pub fn new(app: &tauri::AppHandle, operation: TransferOperation, froms: Vec<String>, to: String) -> Result<Self, String> {
+        let source = froms.iter().map(|from| resolve_live(app, from)).collect::<Result<Vec<_>, String>>()?;
+        let destination = resolve_live(app, &to)?;
+        Ok(Self {
+            operation,
+            froms,
+            to,
+            source,
+            destination,
+        })
+    }
+
+    // A job between local paths only, which needs no connection profiles
+    pub fn local(operation: TransferOperation, froms: Vec<String>, to: String) -> Self {
+        let provider: Arc<dyn Provider> = Arc::new(LocalProvider);
+        Self {
+            operation,
+            source: froms.iter().map(|from| (provider.clone(), from.clone())).collect(),
+            destination: (provider, to.clone()),
+            froms,
+            to,
+        }
+    }
+
+    pub fn run<F: FnMut(TransferStatus) -> Response>(&self, control: &TransferControl, mut on_status: F) {
+        let (total_count, total_size) = self.source.iter().fold((0, 0), |(count, size), (provider, path)| {
+            let (c, s) = measure(provider.as_ref(), path);
+            (count + c, size + s)
+        });
+        on_status(TransferStatus::Ready(total_count, total_size));
+
+        let (destination, to) = &self.destination;
+        for (provider, from) in &self.source {
+            let target = join(to, &file_name(from));
+            if Arc::ptr_eq(provider, destination) && !destination.exists(&target) {
+                let done = match self.operation {
+                    TransferOperation::Move => provider.rename(from, &target).map(|_| true),
+                    TransferOperation::Copy => provider.copy(from, &target),
+                };
+                match done {
+                    Ok(true) => {
+                        on_status(TransferStatus::End);
+                        continue;
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        on_status(TransferStatus::Error(e));
+                        return;
+                    }
+                }
+            }
+            match self.transfer(provider.as_ref(), from, destination.as_ref(), &target, control, &mut on_status) {
+                Ok(Outcome::Done) | Ok(Outcome::Skipped) => {}
+                Ok(Outcome::Cancelled) => return,
+                Err(e) => {
+                    on_status(TransferStatus::Error(e));
+                    return;
+                }
+            }
+        }
+
+        on_status(TransferStatus::Finished);
+    }
+
+    // Moved items are removed as soon as they are transferred, so that skipped ones stay in place
+    fn transfer<F: FnMut(TransferStatus) -> Response>(&self, source: &dyn Provider, from: &str, destination: &dyn Provider, to: &str, control: &TransferControl, on_status: &mut F) -> Result<Outcome, String> {
+        if control.wait() {
+            return Ok(Outcome::Cancelled);
+        }
+
+        let attribute = source.stat(from)?;
+
+        if attribute.is_directory {
+            if !destination.exists(to) {
+                destination.mkdir(to)?;
+            }
+            let mut outcome = Outcome::Done;
+            for (name, _) in source.readdir(from)? {
+                match self.transfer(source, &join(from, &name), destination, &join(to, &name), control, on_status)? {
+                    Outcome::Done => {}
+                    Outcome::Skipped => outcome = Outcome::Skipped,
+                    Outcome::Cancelled => return Ok(Outcome::Cancelled),
+                }
+            }
+            // A folder that still holds skipped items is kept
+            if outcome == Outcome::Done {
+                self.remove_moved(source, from)?;
+            }
+            return Ok(outcome);
+        }
+
+        if destination.exists(to) {
+            match on_status(TransferStatus::Confirm(to.to_string())) {
+                Response::Skip => return Ok(Outcome::Skipped),
+                Response::Cancel => return Ok(Outcome::Cancelled),
+                _ => {}
+            }
+        }
+
+        if matches!(on_status(TransferStatus::Start(from.to_string())), Response::Cancel) {
+            return Ok(Outcome::Cancelled);
+        }
+
+        // Links are recreated rather than followed, which also keeps link loops from recursing forever
+        if attribute.is_symbolic_link && !attribute.link_path.is_empty() && destination.symlink(&attribute.link_path, to)? {
+            on_status(TransferStatus::End);
+            self.remove_moved(source, from)?;
+            return Ok(Outcome::Done);
+        }
+
+        let result = {
+            let mut reader = ProgressReader {
+                inner: source.open(from)?,
+                control,
+                on_status: &mut *on_status,
+            };
+            destination.upload(to, &mut reader, attribute.size)
+        };
+
+        if let Err(e) = result {
+            if control.wait() {
+                return Ok(Outcome::Cancelled);
+            }
+            return Err(e);
+        }
+
+        on_status(TransferStatus::End);
+        self.remove_moved(source, from)?;
+        Ok(Outcome::Done)
+    }
+
+    fn remove_moved(&self, source: &dyn Provider, from: &str) -> Result<(), String> {
+        match self.operation {
+            TransferOperation::Move => source.remove(from),
+            TransferOperation::Copy => Ok(()),
+        }
+    }
+}
+
+fn measure(provider: &dyn Provider, path: &str) -> (u64, u64) {
+    match provider.stat(path) {
+        Ok(attribute) if attribute.is_directory => provider.readdir(path).unwrap_or_default().iter().fold((0, 0), |(count, size), (name, _)| {
+            let (c, s) = measure(provider, &join(path, name));
+            (count + c, size + s)
+        }),
+        Ok(attribute) => (1, attribute.size),
+        Err(_) => (0, 0),
+    }
+}
+
+#[cfg(target_os = "windows")]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct TransferProgress {
+    file: String,
+    processed_count: u64,
+    total_count: u64,
+    processed_size: u64,
+    total_size: u64,
+    finished: bool,
+    error: Option<String>,
+}
+
+// Windows has no GTK progress dialog, so progress is reported to the window instead
+#[cfg(target_os = "windows")]
+pub fn transfer(app: &tauri::AppHandle, window_label: &str, job: TransferJob) -> Result<(), String> {
+    use tauri::{Emitter, EventTarget};
+
+    let app = app.clone();
+    let target = EventTarget::WebviewWindow {
+        label: window_label.to_string(),
+    };
+    std::thread::spawn(move || {
+        let control = TransferControl::default();
+        let mut progress = TransferProgress::default();
+        let mut last_emit = std::time::Instant::now();
+        job.run(&control, |status| {
+            let response = match status {
+                TransferStatus::Ready(total_count, total_size) => {
+                    progress.total_count = total_count;
+                    progress.total_size = total_size;
+                    Response::Proceed
+                }
+                TransferStatus::Start(file) => {
+                    progress.file = file;
+                    Response::Proceed
+                }
+                TransferStatus::Progress(size) => {
+                    progress.processed_size += size;
+                    if last_emit.elapsed() < Duration::from_millis(100) {
+                        return Response::Proceed;
+                    }
+                    Response::Proceed
+                }
+                // Transfers cannot rename into place, so only Replace and Skip are offered
+                TransferStatus::Confirm(file) => {
+                    let buttons = &crate::CONFLICT_BUTTONS[..2];
+                    let result = tauri::async_runtime::block_on(zouni::dialog::message(zouni::dialog::MessageDialogOptions {
+                        title: None,
+                        kind: Some(zouni::dialog::MessageDialogKind::Warning),
+                        buttons: buttons.iter().map(|button| button.to_string()).collect(),
+                        message: format!("{} already exists", file),
+                        cancel_id: None,
+                    }));
+                    match buttons.iter().position(|button| *button == result.button) {
+                        Some(0) => Response::Replace,
+                        Some(1) => Response::Skip,
+                        _ => Response::Cancel,
+                    }
+                }
+                TransferStatus::End => {
+                    progress.processed_count += 1;
+                    Response::Proceed
+                }
+                TransferStatus::Finished => {
+                    progress.finished = true;
+                    Response::Proceed
+                }
+                TransferStatus::Error(e) => {
+                    progress.error = Some(e);
+                    progress.finished = true;
+                    Response::Cancel
+                }
+            };
+            last_emit = std::time::Instant::now();
+            let _ = app.emit_to(target.clone(), TRANSFER_EVENT_NAME, progress.clone());
+            response
+        });
+    });
+    Ok(())
+}
+
+pub fn is_transfer(froms: &[String], to: &str) -> bool {
+    is_remote(to) || froms.iter().any(|from| is_remote(from))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    // Reads a profile from a URL such as "sftp://user@127.0.0.1:2222/tmp" given in the environment
+    pub(crate) fn profile_from_env(variable: &str, auth: Auth) -> (ConnectionProfile, String) {
+        let value = std::env::var(variable).unwrap_or_else(|_| panic!("{} is not set", variable));
+        let url = url::Url::parse(&value).unwrap();
+        let profile = ConnectionProfile {
+            id: "test".to_string(),
+            name: "test".to_string(),
+            scheme: url.scheme().to_string(),
+            host: url.host_str().unwrap().to_string(),
+            port: url.port(),
+            user: url.username().to_string(),
+            auth,
+            root: None,
+        };
+        (profile, url.path().to_string())
+    }
+
+    pub(crate) fn round_trip(provider: &dyn Provider, base: &str) {
+        let directory = join(base, "explite-round-trip");
+        let file = join(&directory, "a b%.txt");
+        let renamed = join(&directory, "renamed.txt");
+        let content = b"round trip";
+
+        provider.mkdir(&directory).unwrap();
+        provider.upload(&file, &mut &content[..], content.len() as u64).unwrap();
+
+        let entries = provider.readdir(&directory).unwrap();
+        assert!(entries.iter().any(|(name, attribute)| name == "a b%.txt" && attribute.is_file && attribute.size == content.len() as u64));
+        assert!(provider.stat(&directory).unwrap().is_directory);
+
+        let mut read = Vec::new();
+        provider.open(&file).unwrap().read_to_end(&mut read).unwrap();
+        assert_eq!(read, content);
+
+        provider.rename(&file, &renamed).unwrap();
+        assert!(!provider.exists(&file));
+        assert!(provider.exists(&renamed));
+
+        provider.remove(&renamed).unwrap();
+        provider.remove(&directory).unwrap();
+        assert!(!provider.exists(&directory));
+    }
+
+    #[test]
+    fn split_uri() {
+        assert_eq!(split("sftp://server/home/user"), Some(("server", "/home/user".to_string())));
+        assert_eq!(split("dav://server"), Some(("server", "/".to_string())));
+        assert_eq!(split("/home/user"), None);
+    }
+
+    #[test]
+    fn connections_are_compared_by_scheme_and_id() {
+        assert_eq!(connection_of("sftp://server/a"), connection_of("sftp://server/b/c"));
+        assert_ne!(connection_of("sftp://server/a"), connection_of("dav://server/a"));
+        assert_ne!(connection_of("sftp://server/a"), connection_of("sftp://other/a"));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn local_transfer_recreates_links() {
+        let source = tempfile::tempdir().unwrap();
+        let destination = tempfile::tempdir().unwrap();
+        let folder = source.path().join("folder");
+        std::fs::create_dir(&folder).unwrap();
+        std::fs::write(folder.join("file.txt"), "content").unwrap();
+        std::os::unix::fs::symlink("..", folder.join("loop")).unwrap();
+        std::os::unix::fs::symlink("file.txt", folder.join("link.txt")).unwrap();
+
+        let job = TransferJob::local(TransferOperation::Copy, vec![folder.to_string_lossy().to_string()], destination.path().to_string_lossy().to_string());
+        job.run(&TransferControl::default(), |_| Response::Proceed);
+
+        let copied = destination.path().join("folder");
+        assert_eq!(std::fs::read_link(copied.join("loop")).unwrap(), Path::new(".."));
+        assert_eq!(std::fs::read_link(copied.join("link.txt")).unwrap(), Path::new("file.txt"));
+        assert_eq!(std::fs::read_to_string(copied.join("file.txt")).unwrap(), "content");
+    }
+
+    #[test]
+    fn local_round_trip() {
+        let base = tempfile::tempdir().unwrap();
+        round_trip(&LocalProvider, &base.path().to_string_lossy());
+    }
+}
@@ -0,0 +1,188 @@
+use crate::{
+    helper::{WindowInit, MAIN_WINDOW},
+    tabs::WindowTabs,
+};
+use serde::{Deserialize, Serialize};
+use std::{path::PathBuf, sync::Mutex, time::Duration};
+use tauri::{AppHandle, Manager};
+
+const SESSION_FILE: &str = "session.json";
+// The session is also saved while running so that little is lost after a crash
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+// The periodic save and the one on exit can overlap
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WindowBounds {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub maximized: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct WindowSession {
+    label: String,
+    bounds: WindowBounds,
+    tabs: WindowTabs,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct SessionFile {
+    // False while the app is running, so a false value on launch means it did not exit normally
+    clean: bool,
+    windows: Vec<WindowSession>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Restorable {
+    pub crashed: bool,
+}
+
+struct RestoreState {
+    previous: Mutex<Option<SessionFile>>,
+    // Last snapshot taken while windows were still open
+    last: Mutex<Vec<WindowSession>>,
+}
+
+fn session_path(app: &AppHandle) -> Option<PathBuf> {
+    app.path().app_data_dir().ok().map(|dir| dir.join(SESSION_FILE))
+}
+
+fn read(app: &AppHandle) -> Option<SessionFile> {
+    let content = std::fs::read_to_string(session_path(app)?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+fn write(app: &AppHandle, session: &SessionFile) -> Result<(), String> {
+    let path = session_path(app).ok_or("App data directory not found")?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let content = serde_json::to_string(session).map_err(|e| e.to_string())?;
+    // Write to a temporary file and rename so a crash mid-write never leaves a truncated session
+    let _lock = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let temp = path.with_extension("json.tmp");
+    std::fs::write(&temp, content).map_err(|e| e.to_string())?;
+    std::fs::rename(&temp, &path).map_err(|e| e.to_string())
+}
+
+pub fn setup(app: &tauri::App) {
+    let app_handle = app.app_handle().clone();
+    let previous = read(&app_handle).filter(|session| !session.windows.is_empty());
+    app.manage(RestoreState {
+        previous: Mutex::new(previous),
+        last: Mutex::new(Vec::new()),
+    });
+
+    tauri::async_runtime::spawn(async move {
+        loop {
+            smol::Timer::after(SAVE_INTERVAL).await;
+            let _ = save(&app_handle, false);
+        }
+    });
+}
+
+fn snapshot(app: &AppHandle) -> Vec<WindowSession> {
+    let mut windows: Vec<WindowSession> = app
+        .webview_windows()
+        .into_values()
+        .filter_map(|window| {
+            let tabs = crate::tabs::list(app, window.label());
+            if tabs.tabs.is_empty() {
+                return None;
+            }
+            let position = window.inner_position().ok()?;
+            let size = window.inner_size().ok()?;
+            Some(WindowSession {
+                label: window.label().to_string(),
+                bounds: WindowBounds {
+                    x: position.x,
+                    y: position.y,
+                    width: size.width,
+                    height: size.height,
+                    maximized: window.is_maximized().unwrap_or_default(),
+                },
+                tabs,
+            })
+        })
+        .collect();
+    // The main window comes first so that it is restored into the main window again
+    windows.sort_by_key(|window| window.label != MAIN_WINDOW);
+    windows
+}
+
+// Keeps the windows that are about to close the app
+pub fn remember(app: &AppHandle) {
+    let Some(state) = app.try_state::<RestoreState>() else {
+        return;
+    };
+    let windows = snapshot(app);
+    if !windows.is_empty() {
+        *state.last.lock().unwrap() = windows;
+    }
+}
+
+pub fn save(app: &AppHandle, clean: bool) -> Result<(), String> {
+    let Some(state) = app.try_state::<RestoreState>() else {
+        return Ok(());
+    };
+
+    let windows = snapshot(app);
+    let windows = if windows.is_empty() {
+        state.last.lock().unwrap().clone()
+    } else {
+        *state.last.lock().unwrap() = windows.clone();
+        windows
+    };
+
+    write(
+        app,
+        &SessionFile {
+            clean,
+            windows,
+        },
+    )
+}
+
+pub fn restorable(app: &AppHandle) -> Option<Restorable> {
+    let state = app.try_state::<RestoreState>()?;
+    let previous = state.previous.lock().unwrap();
+    previous.as_ref().map(|session| Restorable {
+        crashed: !session.clean,
+    })
+}
+
+// Opens the saved windows and returns the bounds for the main window
+pub fn restore(app: &AppHandle, accept: bool) -> Result<Option<WindowBounds>, String> {
+    let Some(state) = app.try_state::<RestoreState>() else {
+        return Ok(None);
+    };
+    let Some(session) = state.previous.lock().unwrap().take() else {
+        return Ok(None);
+    };
+
+    if !accept {
+        return Ok(None);
+    }
+
+    let mut windows = session.windows.into_iter();
+    let Some(main) = windows.next() else {
+        return Ok(None);
+    };
+
+    crate::tabs::restore(app, MAIN_WINDOW, main.tabs);
+    for window in windows {
+        crate::helper::create_window(
+            app,
+            WindowInit {
+                tabs: Some(window.tabs),
+                bounds: Some(window.bounds),
+                ..Default::default()
+            },
+        )?;
+    }
+
+    Ok(Some(main.bounds))
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+use tauri::{AppHandle, Emitter, Manager};
 #[cfg(target_os = "windows")]
 use windows::{
     core::PCWSTR,
@@ -24,12 +26,167 @@ fn encode_wide(string: impl AsRef<std::ffi::OsStr>) -> Vec<u16> {
     string.as_ref().encode_wide().chain(std::iter::once(0)).collect()
 }
 
-pub fn start(id: &str) -> Result<Session, String> {
-    let id = if cfg!(target_os = "linux") {
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct OpenRequest {
+    pub paths: Vec<String>,
+    pub options: HashMap<String, String>,
+}
+
+#[cfg(target_os = "linux")]
+struct SessionService {
+    app: AppHandle,
+}
+
+#[cfg(target_os = "linux")]
+#[zbus::interface(name = "org.explite.Session")]
+impl SessionService {
+    fn open(&self, paths: Vec<String>, options: HashMap<String, String>) {
+        let request = OpenRequest {
+            paths,
+            options,
+        };
+        open(&self.app, request);
+    }
+
+    fn directories(&self) -> Vec<String> {
+        crate::tabs::directories(&self.app)
+    }
+}
+
+#[cfg(target_os = "linux")]
+const FILE_MANAGER_NAME: &str = "org.freedesktop.FileManager1";
+#[cfg(target_os = "linux")]
+const FILE_MANAGER_PATH: &str = "/org/freedesktop/FileManager1";
+
+// Serves "Show in folder" requests from browsers and other apps
+#[cfg(target_os = "linux")]
+struct FileManagerService {
+    app: AppHandle,
+}
+
+#[cfg(target_os = "linux")]
+impl FileManagerService {
+    fn request(&self, uris: Vec<String>, option: Option<&str>) {
+        let paths = uris.into_iter().map(|uri| uri_to_path(&uri)).collect();
+        let options = option.map(|option| HashMap::from([(option.to_string(), "true".to_string())])).unwrap_or_default();
+        let request = OpenRequest {
+            paths,
+            options,
+        };
+        open(&self.app, request);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[zbus::interface(name = "org.freedesktop.FileManager1")]
+impl FileManagerService {
+    fn show_folders(&self, uris: Vec<String>, _startup_id: &str) {
+        self.request(uris, None);
+    }
+
+    fn show_items(&self, uris: Vec<String>, _startup_id: &str) {
+        self.request(uris, Some("reveal"));
+    }
+
+    fn show_item_properties(&self, uris: Vec<String>, _startup_id: &str) {
+        self.request(uris, Some("properties"));
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn uri_to_path(uri: &str) -> String {
+    match url::Url::parse(uri) {
+        Ok(url) if url.scheme() == "file" => url.to_file_path().map(|path| path.to_string_lossy().to_string()).unwrap_or(uri.to_string()),
+        _ => uri.to_string(),
+    }
+}
+
+fn session_name(id: &str) -> String {
+    if cfg!(target_os = "linux") {
         format!("org.{}.session", id.replace(['.', '-'], "_"))
     } else {
         id.to_string()
-    };
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn object_path(name: &str) -> String {
+    format!("/{}", name.replace('.', "/"))
+}
+
+// Launches get a window of their own while reveal and tab requests go to the active window
+pub fn open(app: &AppHandle, request: OpenRequest) {
+    let in_window = ["reveal", "properties", "tab"].iter().any(|key| request.options.contains_key(*key));
+    if request.options.contains_key("new-window") || !in_window {
+        if request.paths.is_empty() {
+            let _ = crate::helper::create_window(
+                app,
+                crate::helper::WindowInit {
+                    options: request.options.clone(),
+                    ..Default::default()
+                },
+            );
+        }
+        for path in request.paths {
+            let _ = crate::helper::create_window(
+                app,
+                crate::helper::WindowInit {
+                    urls: vec![path],
+                    options: request.options.clone(),
+                    ..Default::default()
+                },
+            );
+        }
+        return;
+    }
+
+    let windows = app.webview_windows();
+    let window = windows.values().find(|window| window.is_focused().unwrap_or_default()).or(windows.get(crate::helper::MAIN_WINDOW)).or(windows.values().next());
+    if let Some(window) = window {
+        let _ = window.emit_to(window.label(), "open_request", request);
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+}
+
+// Hands the arguments to the primary instance
+#[allow(unused_variables)]
+pub fn forward(id: &str, request: &OpenRequest) -> Result<(), String> {
+    #[cfg(target_os = "linux")]
+    {
+        let name = session_name(id);
+        let connection = Connection::session().map_err(|e| e.to_string())?;
+        connection.call_method(Some(name.as_str()), object_path(&name).as_str(), Some("org.explite.Session"), "Open", &(&request.paths, &request.options)).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        Err("Forwarding is not supported".to_string())
+    }
+}
+
+// Folders open in the primary instance
+#[allow(unused_variables)]
+pub fn directories(id: &str) -> Result<Vec<String>, String> {
+    #[cfg(target_os = "linux")]
+    {
+        let name = session_name(id);
+        let connection = Connection::session().map_err(|e| e.to_string())?;
+        let reply = connection.call_method(Some(name.as_str()), object_path(&name).as_str(), Some("org.explite.Session"), "Directories", &()).map_err(|e| e.to_string())?;
+        reply.body().deserialize().map_err(|e| e.to_string())
+    }
+
+    #[cfg(target_os = "windows")]
+    {
+        Err("Querying the running instance is not supported".to_string())
+    }
+}
+
+#[allow(unused_variables)]
+pub fn start(app: &AppHandle, id: &str) -> Result<Session, String> {
+    let id = session_name(id);
     #[cfg(target_os = "windows")]
     unsafe {
         let mutex_name = encode_wide(&id);
@@ -45,7 +202,15 @@ pub fn start(id: &str) -> Result<Session, String> {
 
     #[cfg(target_os = "linux")]
     {
-        if let Ok(connection) = Builder::session().unwrap().name(id.clone()).unwrap().replace_existing_names(false).allow_name_replacements(false).build() {
+        let service = SessionService {
+            app: app.clone(),
+        };
+        let file_manager = FileManagerService {
+            app: app.clone(),
+        };
+        let builder = Builder::session().map_err(|e| e.to_string())?.serve_at(object_path(&id), service).map_err(|e| e.to_string())?;
+        let builder = builder.serve_at(FILE_MANAGER_PATH, file_manager).map_err(|e| e.to_string())?;
+        if let Ok(connection) = builder.name(id.clone()).unwrap().replace_existing_names(false).allow_name_replacements(false).build() {
             Ok(Session {
                 connection,
                 id,
@@ -56,6 +221,16 @@ pub fn start(id: &str) -> Result<Session, String> {
     }
 }
 
+// The well-known name is only claimed when the user opts in
+#[cfg(target_os = "linux")]
+pub fn claim_file_manager(session: &Session, claim: bool) -> Result<(), String> {
+    if claim {
+        session.connection.request_name(FILE_MANAGER_NAME).map_err(|e| e.to_string())
+    } else {
+        session.connection.release_name(FILE_MANAGER_NAME).map(|_| ()).map_err(|e| e.to_string())
+    }
+}
+
 pub fn end(session: &Session) {
     #[cfg(target_os = "windows")]
     unsafe {
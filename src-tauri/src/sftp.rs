@@ -0,0 +1,254 @@
+use crate::remote::{Auth, ConnectionProfile, FileAttribute, Provider};
+use base64::{engine::general_purpose::STANDARD_NO_PAD, Engine};
+use ssh2::{CheckResult, ErrorCode, FileStat, HashType, KnownHostFileKind, KnownHosts, OpenFlags, OpenType, Session, Sftp};
+use std::{
+    io::Read,
+    net::{TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const DEFAULT_PORT: u16 = 22;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+const DIRECTORY_MODE: i32 = 0o755;
+const FILE_MODE: i32 = 0o644;
+#[cfg(target_os = "linux")]
+const SSH_DIRECTORY_MODE: u32 = 0o700;
+
+pub struct SftpProvider {
+    _session: Session,
+    sftp: Sftp,
+}
+
+impl SftpProvider {
+    pub fn connect(profile: &ConnectionProfile) -> Result<Self, String> {
+        let session = handshake(profile)?;
+        verify_host_key(&session, profile)?;
+
+        match &profile.auth {
+            Auth::Agent => session.userauth_agent(&profile.user).map_err(map_ssh_error)?,
+            Auth::Key {
+                private_key,
+                passphrase,
+            } => session.userauth_pubkey_file(&profile.user, None, Path::new(private_key), passphrase.as_deref()).map_err(map_ssh_error)?,
+            _ => return Err("SFTP supports agent or key authentication".to_string()),
+        }
+
+        if !session.authenticated() {
+            return Err("Authentication failed".to_string());
+        }
+
+        let sftp = session.sftp().map_err(map_ssh_error)?;
+
+        Ok(Self {
+            _session: session,
+            sftp,
+        })
+    }
+}
+
+// Unreachable hosts fail after CONNECT_TIMEOUT instead of waiting for the system TCP timeout
+fn open_stream(profile: &ConnectionProfile) -> Result<TcpStream, String> {
+    let addresses = (profile.host.as_str(), profile.port.unwrap_or(DEFAULT_PORT)).to_socket_addrs().map_err(|e| e.to_string())?;
+    let mut error = format!("Failed to resolve {}", profile.host);
+    for address in addresses {
+        match TcpStream::connect_timeout(&address, CONNECT_TIMEOUT) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => error = e.to_string(),
+        }
+    }
+    Err(error)
+}
+
+fn handshake(profile: &ConnectionProfile) -> Result<Session, String> {
+    let stream = open_stream(profile)?;
+    let mut session = Session::new().map_err(map_ssh_error)?;
+    session.set_tcp_stream(stream);
+    session.handshake().map_err(map_ssh_error)?;
+    Ok(session)
+}
+
+fn known_hosts_file() -> Option<PathBuf> {
+    std::env::var_os("HOME").or(std::env::var_os("USERPROFILE")).map(|home| PathBuf::from(home).join(".ssh").join("known_hosts"))
+}
+
+fn load_known_hosts(session: &Session) -> Result<KnownHosts, String> {
+    let mut known_hosts = session.known_hosts().map_err(map_ssh_error)?;
+    if let Some(file) = known_hosts_file().filter(|file| file.exists()) {
+        known_hosts.read_file(&file, KnownHostFileKind::OpenSSH).map_err(map_ssh_error)?;
+    }
+    Ok(known_hosts)
+}
+
+fn check_host_key(session: &Session, profile: &ConnectionProfile) -> Result<CheckResult, String> {
+    let (key, _) = session.host_key().ok_or("Host key not available".to_string())?;
+    Ok(load_known_hosts(session)?.check_port(&profile.host, profile.port.unwrap_or(DEFAULT_PORT), key))
+}
+
+// Only hosts recorded in ~/.ssh/known_hosts are accepted, unknown ones must be trusted by the user first
+fn verify_host_key(session: &Session, profile: &ConnectionProfile) -> Result<(), String> {
+    match check_host_key(session, profile)? {
+        CheckResult::Match => Ok(()),
+        CheckResult::NotFound => Err(format!("Host key for {} is not trusted", profile.host)),
+        CheckResult::Mismatch => Err(format!("Host key for {} does not match known_hosts", profile.host)),
+        CheckResult::Failure => Err(format!("Failed to verify host key for {}", profile.host)),
+    }
+}
+
+// Returns the fingerprint of the host key when known_hosts has no entry for the host yet
+pub fn unknown_host_key(profile: &ConnectionProfile) -> Result<Option<String>, String> {
+    let session = handshake(profile)?;
+    if !matches!(check_host_key(&session, profile)?, CheckResult::NotFound) {
+        return Ok(None);
+    }
+    Ok(Some(fingerprint(&session)?))
+}
+
+fn fingerprint(session: &Session) -> Result<String, String> {
+    let hash = session.host_key_hash(HashType::Sha256).ok_or("Host key not available".to_string())?;
+    Ok(format!("SHA256:{}", STANDARD_NO_PAD.encode(hash)))
+}
+
+// Records the host key in known_hosts after the user confirmed its fingerprint.
+// The key is read over a new connection, so it must be the one the user saw
+pub fn trust_host_key(profile: &ConnectionProfile, approved: &str) -> Result<(), String> {
+    let session = handshake(profile)?;
+    if !matches!(check_host_key(&session, profile)?, CheckResult::NotFound) {
+        return Ok(());
+    }
+    if fingerprint(&session)? != approved {
+        return Err(format!("Host key for {} changed before it was trusted", profile.host));
+    }
+
+    let (key, key_type) = session.host_key().ok_or("Host key not available".to_string())?;
+    let port = profile.port.unwrap_or(DEFAULT_PORT);
+    let host = if port == DEFAULT_PORT {
+        profile.host.clone()
+    } else {
+        format!("[{}]:{}", profile.host, port)
+    };
+    let mut known_hosts = load_known_hosts(&session)?;
+    known_hosts.add(&host, key, "", key_type.into()).map_err(map_ssh_error)?;
+
+    let file = known_hosts_file().ok_or("Home directory not found".to_string())?;
+    if let Some(directory) = file.parent().filter(|directory| !directory.exists()) {
+        std::fs::create_dir_all(directory).map_err(|e| e.to_string())?;
+        #[cfg(target_os = "linux")]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::set_permissions(directory, std::fs::Permissions::from_mode(SSH_DIRECTORY_MODE)).map_err(|e| e.to_string())?;
+        }
+    }
+    known_hosts.write_file(&file, KnownHostFileKind::OpenSSH).map_err(map_ssh_error)
+}
+
+fn to_attribute(stat: &FileStat) -> FileAttribute {
+    let mtime_ms = stat.mtime.unwrap_or_default() as f64 * 1000.0;
+    FileAttribute {
+        is_directory: stat.is_dir(),
+        is_file: stat.is_file(),
+        is_symbolic_link: stat.file_type().is_symlink(),
+        is_read_only: stat.perm.map(|perm| perm & 0o200 == 0).unwrap_or_default(),
+        atime_ms: stat.atime.unwrap_or_default() as f64 * 1000.0,
+        mtime_ms,
+        ctime_ms: mtime_ms,
+        birthtime_ms: mtime_ms,
+        size: stat.size.unwrap_or_default(),
+        ..Default::default()
+    }
+}
+
+impl Provider for SftpProvider {
+    fn readdir(&self, directory: &str) -> Result<Vec<(String, FileAttribute)>, String> {
+        let entries = self.sftp.readdir(Path::new(directory)).map_err(map_ssh_error)?;
+        Ok(entries
+            .into_iter()
+            .filter_map(|(path, stat)| {
+                let name = path.file_name()?.to_string_lossy().to_string();
+                let mut attribute = to_attribute(&stat);
+                // Follow symlinks so linked folders can be opened
+                if attribute.is_symbolic_link {
+                    if let Ok(target) = self.sftp.stat(&path) {
+                        attribute.is_directory = target.is_dir();
+                        attribute.is_file = target.is_file();
+                    }
+                    attribute.link_path = self.sftp.readlink(&path).map(|link| link.to_string_lossy().to_string()).unwrap_or_default();
+                }
+                Some((name, attribute))
+            })
+            .collect())
+    }
+
+    fn stat(&self, path: &str) -> Result<FileAttribute, String> {
+        self.sftp.stat(Path::new(path)).map(|stat| to_attribute(&stat)).map_err(map_ssh_error)
+    }
+
+    fn mkdir(&self, path: &str) -> Result<(), String> {
+        self.sftp.mkdir(Path::new(path), DIRECTORY_MODE).map_err(map_ssh_error)
+    }
+
+    fn rename(&self, from: &str, to: &str) -> Result<(), String> {
+        self.sftp.rename(Path::new(from), Path::new(to), None).map_err(map_ssh_error)
+    }
+
+    fn remove(&self, path: &str) -> Result<(), String> {
+        let stat = self.sftp.lstat(Path::new(path)).map_err(map_ssh_error)?;
+        if !stat.is_dir() {
+            return self.sftp.unlink(Path::new(path)).map_err(map_ssh_error);
+        }
+
+        for (child, _) in self.sftp.readdir(Path::new(path)).map_err(map_ssh_error)? {
+            self.remove(&child.to_string_lossy())?;
+        }
+        self.sftp.rmdir(Path::new(path)).map_err(map_ssh_error)
+    }
+
+    fn open(&self, path: &str) -> Result<Box<dyn Read + Send>, String> {
+        Ok(Box::new(self.sftp.open(Path::new(path)).map_err(map_ssh_error)?))
+    }
+
+    fn upload(&self, path: &str, reader: &mut dyn Read, _size: u64) -> Result<(), String> {
+        let mut file = self.sftp.open_mode(Path::new(path), OpenFlags::WRITE | OpenFlags::CREATE | OpenFlags::TRUNCATE, FILE_MODE, OpenType::File).map_err(map_ssh_error)?;
+        std::io::copy(reader, &mut file).map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    // Errors of the session itself, rather than of an SFTP request, mean the connection is gone
+    fn is_closed(&self) -> bool {
+        self.sftp.lstat(Path::new(".")).is_err_and(|e| matches!(e.code(), ErrorCode::Session(_)))
+    }
+}
+
+fn map_ssh_error(e: ssh2::Error) -> String {
+    e.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::remote::tests::{profile_from_env, round_trip};
+
+    // e.g. EXPLITE_TEST_SFTP=sftp://user@127.0.0.1:2222/tmp against a loopback sshd, with an optional EXPLITE_TEST_SFTP_KEY
+    #[test]
+    #[ignore = "needs an SSH server listed in known_hosts, set EXPLITE_TEST_SFTP"]
+    fn sftp_round_trip() {
+        let auth = match std::env::var("EXPLITE_TEST_SFTP_KEY") {
+            Ok(private_key) => Auth::Key {
+                private_key,
+                passphrase: None,
+            },
+            Err(_) => Auth::Agent,
+        };
+        let (profile, base) = profile_from_env("EXPLITE_TEST_SFTP", auth);
+        assert_eq!(unknown_host_key(&profile).unwrap(), None);
+        round_trip(&SftpProvider::connect(&profile).unwrap(), &base);
+    }
+
+    #[test]
+    #[ignore = "needs an SSH server missing from known_hosts, set EXPLITE_TEST_SFTP_UNKNOWN"]
+    fn unknown_host_is_rejected() {
+        let (profile, _) = profile_from_env("EXPLITE_TEST_SFTP_UNKNOWN", Auth::Agent);
+        assert!(unknown_host_key(&profile).unwrap().is_some_and(|fingerprint| fingerprint.starts_with("SHA256:")));
+        assert!(SftpProvider::connect(&profile).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, sync::Mutex};
+use tauri::{AppHandle, Manager};
+
+// Closed tabs kept per window for reopening
+const MAX_CLOSED_TABS: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SortType {
+    pub key: String,
+    pub asc: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tab {
+    #[serde(default)]
+    pub id: u32,
+    pub path: String,
+    #[serde(default)]
+    pub back: Vec<String>,
+    #[serde(default)]
+    pub forward: Vec<String>,
+    pub sort: SortType,
+    #[serde(default)]
+    pub selection: Vec<String>,
+    #[serde(default)]
+    pub scroll: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WindowTabs {
+    pub tabs: Vec<Tab>,
+    pub active: u32,
+    #[serde(skip)]
+    closed: Vec<Tab>,
+    #[serde(skip)]
+    next_id: u32,
+}
+
+impl WindowTabs {
+    fn add(&mut self, mut tab: Tab) -> Tab {
+        self.next_id += 1;
+        tab.id = self.next_id;
+        // New tabs open next to the active one
+        let index = self.tabs.iter().position(|tab| tab.id == self.active).map(|index| index + 1).unwrap_or(self.tabs.len());
+        self.tabs.insert(index, tab.clone());
+        self.active = tab.id;
+        tab
+    }
+
+    fn take(&mut self, id: u32) -> Option<Tab> {
+        let index = self.tabs.iter().position(|tab| tab.id == id)?;
+        let tab = self.tabs.remove(index);
+        if self.active == id {
+            // The right neighbour takes over, or the left one when the last tab was closed
+            self.active = self.tabs.get(index).or(self.tabs.last()).map(|tab| tab.id).unwrap_or_default();
+        }
+        Some(tab)
+    }
+
+    fn active_tab(&self) -> Option<Tab> {
+        self.tabs.iter().find(|tab| tab.id == self.active).cloned()
+    }
+}
+
+impl From<Tab> for WindowTabs {
+    fn from(tab: Tab) -> Self {
+        Self {
+            active: tab.id,
+            tabs: vec![tab],
+            ..Default::default()
+        }
+    }
+}
+
+// Tabs keyed by window label
+pub struct Tabs(Mutex<HashMap<String, WindowTabs>>);
+
+pub fn setup(app: &tauri::App) {
+    app.manage(Tabs(Mutex::new(HashMap::new())));
+}
+
+fn with_window<T>(app: &AppHandle, label: &str, f: impl FnOnce(&mut WindowTabs) -> T) -> T {
+    let state = app.state::<Tabs>();
+    let mut windows = state.0.lock().unwrap();
+    f(windows.entry(label.to_string()).or_default())
+}
+
+pub fn list(app: &AppHandle, label: &str) -> WindowTabs {
+    with_window(app, label, |window| window.clone())
+}
+
+pub fn active(app: &AppHandle, label: &str) -> u32 {
+    with_window(app, label, |window| window.active)
+}
+
+pub fn open(app: &AppHandle, label: &str, tab: Tab) -> Tab {
+    with_window(app, label, |window| window.add(tab))
+}
+
+pub fn update(app: &AppHandle, label: &str, tab: Tab) {
+    with_window(app, label, |window| {
+        if let Some(current) = window.tabs.iter_mut().find(|current| current.id == tab.id) {
+            *current = tab;
+        }
+    })
+}
+
+pub fn activate(app: &AppHandle, label: &str, id: u32) -> Option<Tab> {
+    with_window(app, label, |window| {
+        let tab = window.tabs.iter().find(|tab| tab.id == id).cloned()?;
+        window.active = id;
+        Some(tab)
+    })
+}
+
+// Returns the tab that becomes active
+pub fn close(app: &AppHandle, label: &str, id: u32) -> Option<Tab> {
+    with_window(app, label, |window| {
+        if let Some(tab) = window.take(id) {
+            window.closed.push(tab);
+            if window.closed.len() > MAX_CLOSED_TABS {
+                window.closed.remove(0);
+            }
+        }
+        window.active_tab()
+    })
+}
+
+pub fn reopen(app: &AppHandle, label: &str) -> Option<Tab> {
+    with_window(app, label, |window| {
+        let tab = window.closed.pop()?;
+        Some(window.add(tab))
+    })
+}
+
+// Moves the tab into a window of its own and returns the tab that becomes active
+pub fn detach(app: &AppHandle, label: &str, id: u32) -> Result<Option<Tab>, String> {
+    let tab = with_window(app, label, |window| window.tabs.iter().any(|tab| tab.id != id).then(|| window.take(id)).flatten()).ok_or("Cannot move the last tab")?;
+    crate::helper::create_window(
+        app,
+        crate::helper::WindowInit {
+            urls: vec![tab.path.clone()],
+            tabs: Some(WindowTabs::from(tab)),
+            ..Default::default()
+        },
+    )?;
+    Ok(with_window(app, label, |window| window.active_tab()))
+}
+
+// Replaces the tabs of the window with saved or moved ones
+pub fn restore(app: &AppHandle, label: &str, mut tabs: WindowTabs) {
+    tabs.next_id = tabs.tabs.iter().map(|tab| tab.id).max().unwrap_or_default();
+    if tabs.active_tab().is_none() {
+        tabs.active = tabs.tabs.first().map(|tab| tab.id).unwrap_or_default();
+    }
+    with_window(app, label, |window| *window = tabs)
+}
+
+// Folders of every tab in every window without duplicates
+pub fn directories(app: &AppHandle) -> Vec<String> {
+    let state = app.state::<Tabs>();
+    let windows = state.0.lock().unwrap();
+    let mut directories: Vec<String> = Vec::new();
+    for path in windows.values().flat_map(|window| window.tabs.iter().map(|tab| tab.path.clone())) {
+        if !path.is_empty() && !directories.contains(&path) {
+            directories.push(path);
+        }
+    }
+    directories
+}
+
+pub fn forget_window(app: &AppHandle, label: &str) {
+    app.state::<Tabs>().0.lock().unwrap().remove(label);
+}
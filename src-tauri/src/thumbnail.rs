@@ -0,0 +1,435 @@
+#[cfg(target_os = "linux")]
+use crate::gtk_thumb::ThumbnailSize;
+use crate::ThumbnailArgs;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Condvar, Mutex,
+    },
+};
+use tauri::{AppHandle, Emitter, Manager};
+
+const THUMBNAIL_EVENT_NAME: &str = "thumbnail_event";
+const PREFETCH_EVENT_NAME: &str = "thumbnail_prefetch_event";
+// Prefetched items wait behind everything visible
+const PREFETCH_PRIORITY: u32 = 1;
+const MIN_WORKERS: usize = 2;
+const MAX_WORKERS: usize = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThumbnailRequest {
+    pub request_id: String,
+    pub full_path: String,
+    pub video: bool,
+    pub width: u32,
+    pub height: u32,
+    // Lower runs first, visible items use 0
+    pub priority: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ThumbnailEvent {
+    request_id: String,
+    data: Option<Vec<u8>>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedThumbnail {
+    full_path: String,
+    thumbnail_path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct PrefetchEvent {
+    directory: String,
+    full_path: String,
+    thumbnail_path: Option<String>,
+    data: Option<Vec<u8>>,
+    done: usize,
+    total: usize,
+}
+
+// Identical requests share one job
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct JobKey {
+    full_path: String,
+    video: bool,
+    width: u32,
+    height: u32,
+}
+
+#[derive(Debug, Clone)]
+struct Subscriber {
+    label: String,
+    request_id: String,
+    prefetch: Option<Arc<Prefetch>>,
+}
+
+#[derive(Debug)]
+struct Prefetch {
+    directory: String,
+    total: usize,
+    done: AtomicUsize,
+}
+
+struct Job {
+    key: JobKey,
+    priority: u32,
+    sequence: u64,
+    subscribers: Vec<Subscriber>,
+}
+
+#[derive(Default)]
+struct Queue {
+    pending: Vec<Job>,
+    running: HashMap<JobKey, Vec<Subscriber>>,
+    sequence: u64,
+}
+
+impl Queue {
+    fn add(&mut self, key: JobKey, subscriber: Subscriber, priority: u32) {
+        self.sequence += 1;
+        let sequence = self.sequence;
+
+        if let Some(subscribers) = self.running.get_mut(&key) {
+            subscribers.push(subscriber);
+        } else if let Some(job) = self.pending.iter_mut().find(|job| job.key == key) {
+            job.subscribers.push(subscriber);
+            job.priority = job.priority.min(priority);
+            job.sequence = sequence;
+        } else {
+            self.pending.push(Job {
+                key,
+                priority,
+                sequence,
+                subscribers: vec![subscriber],
+            });
+        }
+    }
+
+    // Highest priority first, and the latest request first among equals since it is most likely on screen
+    fn take_next(&mut self) -> Option<Job> {
+        let index = self.pending.iter().enumerate().min_by_key(|(_, job)| (job.priority, std::cmp::Reverse(job.sequence))).map(|(index, _)| index)?;
+        Some(self.pending.swap_remove(index))
+    }
+
+    fn remove_subscribers(&mut self, matches: impl Fn(&Subscriber) -> bool) {
+        self.pending.iter_mut().for_each(|job| job.subscribers.retain(|subscriber| !matches(subscriber)));
+        self.pending.retain(|job| !job.subscribers.is_empty());
+        self.running.values_mut().for_each(|subscribers| subscribers.retain(|subscriber| !matches(subscriber)));
+    }
+}
+
+#[derive(Default)]
+struct ThumbnailPool {
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+pub struct Thumbnails(Arc<ThumbnailPool>);
+
+pub fn setup(app: &tauri::App) {
+    let pool = Arc::new(ThumbnailPool::default());
+    let workers = std::thread::available_parallelism().map(|count| count.get()).unwrap_or(MIN_WORKERS).clamp(MIN_WORKERS, MAX_WORKERS);
+    for _ in 0..workers {
+        let pool = pool.clone();
+        let app_handle = app.app_handle().clone();
+        std::thread::spawn(move || work(&app_handle, &pool));
+    }
+    app.manage(Thumbnails(pool));
+}
+
+pub fn request(app: &AppHandle, label: &str, requests: Vec<ThumbnailRequest>) {
+    let pool = &app.state::<Thumbnails>().0;
+    let mut guard = pool.queue.lock().unwrap();
+    let queue = &mut *guard;
+
+    for request in requests {
+        let key = JobKey {
+            full_path: request.full_path,
+            video: request.video,
+            width: request.width,
+            height: request.height,
+        };
+        let subscriber = Subscriber {
+            label: label.to_string(),
+            request_id: request.request_id,
+            prefetch: None,
+        };
+        queue.add(key, subscriber, request.priority);
+    }
+
+    drop(guard);
+    pool.available.notify_all();
+}
+
+// Returns what is already in the cache and queues the rest of the pictures and videos in the directory
+pub fn prefetch(app: &AppHandle, label: &str, directory: String, size: u32) -> Result<Vec<CachedThumbnail>, String> {
+    // Remote and virtual folders are left to the visible items
+    if !std::path::Path::new(&directory).is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let dirents = zouni::fs::readdir(&directory, false, true)?;
+    let files: Vec<(String, bool)> = dirents
+        .into_iter()
+        .filter(|dirent| !dirent.attributes.is_directory)
+        .filter_map(|dirent| {
+            let video = dirent.mime_type.starts_with("video/");
+            (video || dirent.mime_type.starts_with("image/") || has_thumbnailer(&dirent.mime_type)).then_some((dirent.full_path, video))
+        })
+        .collect();
+
+    let mut hits = Vec::new();
+    let mut misses = Vec::new();
+    for (full_path, video) in files {
+        match cached_thumbnail(&full_path, size) {
+            Some(thumbnail_path) => hits.push(CachedThumbnail {
+                full_path,
+                thumbnail_path,
+            }),
+            None => misses.push((full_path, video)),
+        }
+    }
+
+    let pool = &app.state::<Thumbnails>().0;
+    let mut guard = pool.queue.lock().unwrap();
+    let queue = &mut *guard;
+
+    // Only the latest directory of each window is prefetched
+    queue.remove_subscribers(|subscriber| subscriber.label == label && subscriber.prefetch.is_some());
+
+    let prefetch = Arc::new(Prefetch {
+        directory,
+        total: misses.len(),
+        done: AtomicUsize::new(0),
+    });
+    for (full_path, video) in misses {
+        let key = JobKey {
+            full_path: full_path.clone(),
+            video,
+            width: size,
+            height: size,
+        };
+        let subscriber = Subscriber {
+            label: label.to_string(),
+            request_id: full_path,
+            prefetch: Some(prefetch.clone()),
+        };
+        queue.add(key, subscriber, PREFETCH_PRIORITY);
+    }
+
+    drop(guard);
+    pool.available.notify_all();
+
+    Ok(hits)
+}
+
+// Jobs nobody waits for any more are dropped, running ones finish without reporting
+pub fn cancel(app: &AppHandle, label: &str, request_ids: Vec<String>) {
+    let pool = &app.state::<Thumbnails>().0;
+    pool.queue.lock().unwrap().remove_subscribers(|subscriber| subscriber.label == label && request_ids.contains(&subscriber.request_id));
+}
+
+pub fn forget_window(app: &AppHandle, label: &str) {
+    if let Some(thumbnails) = app.try_state::<Thumbnails>() {
+        thumbnails.0.queue.lock().unwrap().remove_subscribers(|subscriber| subscriber.label == label);
+    }
+}
+
+fn work(app: &AppHandle, pool: &ThumbnailPool) {
+    loop {
+        let key = {
+            let mut queue = pool.queue.lock().unwrap();
+            let job = loop {
+                if let Some(job) = queue.take_next() {
+                    break job;
+                }
+                queue = pool.available.wait(queue).unwrap();
+            };
+            queue.running.insert(job.key.clone(), job.subscribers);
+            job.key
+        };
+
+        let result = tauri::async_runtime::block_on(generate(&key));
+
+        let subscribers = pool.queue.lock().unwrap().running.remove(&key).unwrap_or_default();
+        for subscriber in subscribers {
+            if let Some(prefetch) = subscriber.prefetch {
+                // The image bytes are sent only when there is no cached file the window can load itself
+                let thumbnail_path = cached_thumbnail(&key.full_path, key.width.max(key.height));
+                let event = PrefetchEvent {
+                    directory: prefetch.directory.clone(),
+                    data: result.as_ref().ok().filter(|_| thumbnail_path.is_none()).cloned(),
+                    thumbnail_path,
+                    full_path: subscriber.request_id,
+                    done: prefetch.done.fetch_add(1, Ordering::Relaxed) + 1,
+                    total: prefetch.total,
+                };
+                let _ = app.emit_to(subscriber.label.as_str(), PREFETCH_EVENT_NAME, event);
+                continue;
+            }
+
+            let event = ThumbnailEvent {
+                request_id: subscriber.request_id,
+                data: result.as_ref().ok().cloned(),
+                error: result.as_ref().err().cloned(),
+            };
+            let _ = app.emit_to(subscriber.label.as_str(), THUMBNAIL_EVENT_NAME, event);
+        }
+    }
+}
+
+async fn generate(key: &JobKey) -> Result<Vec<u8>, String> {
+    if key.video {
+        let args = ThumbnailArgs {
+            full_path: key.full_path.clone(),
+            width: key.width,
+            height: key.height,
+        };
+        #[cfg(target_os = "windows")]
+        {
+            crate::helper::video_thumbnail(args).await
+        }
+        #[cfg(target_os = "linux")]
+        {
+            // Recorded failures are not retried with any decoder until the file is modified
+            if crate::gtk_thumb::has_failed(&key.full_path) {
+                return Err("Thumbnail could not be created before".to_string());
+            }
+            // Written to the freedesktop cache so that the next visit and other apps can reuse it
+            match crate::gtk_thumb::video_thumbnail(&key.full_path, ThumbnailSize::nearest(key.width, key.height), Some("explite")) {
+                Err(_) => crate::helper::video_thumbnail(args).await.inspect_err(|_| crate::gtk_thumb::record_failure(&key.full_path, Some("explite"))),
+                result => result,
+            }
+        }
+    } else {
+        #[cfg(target_os = "windows")]
+        {
+            crate::helper::image_thumbnail(key.full_path.clone()).await
+        }
+        #[cfg(target_os = "linux")]
+        {
+            // Other than pictures are left to the thumbnailers installed on the system
+            let mime_type = zouni::fs::get_mime_type(&key.full_path);
+            if !mime_type.starts_with("image/") {
+                return crate::gtk_thumb::external_thumbnail(&key.full_path, &mime_type, ThumbnailSize::nearest(key.width, key.height), Some("explite"));
+            }
+            if crate::gtk_thumb::has_failed(&key.full_path) {
+                return Err("Thumbnail could not be created before".to_string());
+            }
+            match crate::gtk_thumb::image_thumbnail(&key.full_path, ThumbnailSize::nearest(key.width, key.height), Some("explite")) {
+                Err(_) => crate::helper::image_thumbnail(key.full_path.clone()).await.inspect_err(|_| crate::gtk_thumb::record_failure(&key.full_path, Some("explite"))),
+                result => result,
+            }
+        }
+    }
+}
+
+fn has_thumbnailer(mime_type: &str) -> bool {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = mime_type;
+        false
+    }
+    #[cfg(target_os = "linux")]
+    {
+        crate::thumbnailer::find(mime_type).is_some()
+    }
+}
+
+pub fn thumbnailer_mime_types() -> Vec<String> {
+    #[cfg(target_os = "windows")]
+    {
+        Vec::new()
+    }
+    #[cfg(target_os = "linux")]
+    {
+        crate::thumbnailer::mime_types()
+    }
+}
+
+fn cached_thumbnail(full_path: &str, size: u32) -> Option<String> {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = (full_path, size);
+        None
+    }
+    #[cfg(target_os = "linux")]
+    {
+        crate::gtk_thumb::cached(full_path, ThumbnailSize::nearest(size, size)).map(|path| path.to_string_lossy().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(full_path: &str) -> JobKey {
+        JobKey {
+            full_path: full_path.to_string(),
+            video: false,
+            width: 128,
+            height: 128,
+        }
+    }
+
+    fn subscriber(request_id: &str) -> Subscriber {
+        Subscriber {
+            label: "main".to_string(),
+            request_id: request_id.to_string(),
+            prefetch: None,
+        }
+    }
+
+    #[test]
+    fn identical_requests_share_one_job() {
+        let mut queue = Queue::default();
+        queue.add(key("/a.png"), subscriber("1"), 1);
+        queue.add(key("/a.png"), subscriber("2"), 0);
+
+        let job = queue.take_next().unwrap();
+        assert_eq!(job.key, key("/a.png"));
+        assert_eq!(job.priority, 0);
+        assert_eq!(job.subscribers.len(), 2);
+        assert!(queue.take_next().is_none());
+    }
+
+    #[test]
+    fn requests_for_running_jobs_wait_for_them() {
+        let mut queue = Queue::default();
+        queue.running.insert(key("/a.png"), vec![subscriber("1")]);
+        queue.add(key("/a.png"), subscriber("2"), 0);
+
+        assert!(queue.take_next().is_none());
+        assert_eq!(queue.running[&key("/a.png")].len(), 2);
+    }
+
+    #[test]
+    fn priority_comes_first_then_latest_request() {
+        let mut queue = Queue::default();
+        queue.add(key("/prefetch.png"), subscriber("1"), PREFETCH_PRIORITY);
+        queue.add(key("/old.png"), subscriber("2"), 0);
+        queue.add(key("/new.png"), subscriber("3"), 0);
+        // Requested again, so it is now the latest
+        queue.add(key("/old.png"), subscriber("4"), 0);
+
+        let order: Vec<String> = std::iter::from_fn(|| queue.take_next()).map(|job| job.key.full_path).collect();
+        assert_eq!(order, ["/old.png", "/new.png", "/prefetch.png"]);
+    }
+
+    #[test]
+    fn jobs_without_subscribers_are_dropped() {
+        let mut queue = Queue::default();
+        queue.add(key("/a.png"), subscriber("1"), 0);
+        queue.add(key("/b.png"), subscriber("2"), 0);
+        queue.remove_subscribers(|subscriber| subscriber.request_id == "1");
+
+        let job = queue.take_next().unwrap();
+        assert_eq!(job.key, key("/b.png"));
+        assert!(queue.take_next().is_none());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::gtk_thumb;
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+use tauri::Manager;
+use url::Url;
+
+const DAY_SECS: u64 = 24 * 60 * 60;
+const MB: f64 = 1024.0 * 1024.0;
+const SIZE_DIRECTORIES: [&str; 4] = ["normal", "large", "x-large", "xx-large"];
+// Holds one directory per application
+const FAIL_DIRECTORY: &str = "fail";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ThumbnailCachePolicy {
+    max_age_days: Option<u32>,
+    max_size_mb: Option<f64>,
+}
+
+pub struct ThumbnailCachePolicyState(Mutex<ThumbnailCachePolicy>);
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CleanResult {
+    removed_count: u64,
+    removed_size: u64,
+    remaining_count: u64,
+    remaining_size: u64,
+    failed_count: u64,
+}
+
+struct CacheEntry {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+pub fn setup(app: &tauri::App) {
+    app.manage(ThumbnailCachePolicyState(Mutex::new(ThumbnailCachePolicy::default())));
+}
+
+pub fn set_policy(app: &tauri::AppHandle, policy: ThumbnailCachePolicy) {
+    *app.state::<ThumbnailCachePolicyState>().0.lock().unwrap() = policy;
+    run_in_background(app);
+}
+
+pub fn run_in_background(app: &tauri::AppHandle) {
+    let policy = app.state::<ThumbnailCachePolicyState>().0.lock().unwrap().clone();
+    std::thread::spawn(move || {
+        if let Err(e) = clean(&policy) {
+            eprintln!("Thumbnail cache cleanup error: {:?}", e);
+        }
+    });
+}
+
+fn cache_files(root: &Path) -> Vec<PathBuf> {
+    let fail_directories: Vec<PathBuf> =
+        std::fs::read_dir(root.join(FAIL_DIRECTORY)).map(|entries| entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect()).unwrap_or_default();
+
+    SIZE_DIRECTORIES
+        .iter()
+        .map(|directory| root.join(directory))
+        .chain(fail_directories)
+        .filter_map(|directory| std::fs::read_dir(directory).ok())
+        .flat_map(|entries| entries.flatten().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|extension| extension == "png"))
+        .collect()
+}
+
+// Only local sources can be checked, thumbnails of other locations are left to the age and size limits
+fn is_orphaned(thumbnail: &Path) -> bool {
+    gtk_thumb::source_uri(thumbnail).and_then(|uri| Url::parse(&uri).ok()).and_then(|url| url.to_file_path().ok()).is_some_and(|source| !source.exists())
+}
+
+// Thumbnails that cannot be removed, e.g. ones owned by another user, are counted and left in place
+fn remove(entry: &CacheEntry, result: &mut CleanResult) -> bool {
+    if std::fs::remove_file(&entry.path).is_err() {
+        result.failed_count += 1;
+        return false;
+    }
+    result.removed_count += 1;
+    result.removed_size += entry.size;
+    true
+}
+
+// Removes orphaned and expired thumbnails, then the oldest ones until the cache fits in the size limit
+pub fn clean(policy: &ThumbnailCachePolicy) -> Result<CleanResult, String> {
+    let root = gtk_thumb::cache_directory()?;
+    let now = SystemTime::now();
+    let max_age = policy.max_age_days.map(|days| Duration::from_secs(days as u64 * DAY_SECS));
+    let mut result = CleanResult::default();
+    let mut entries = Vec::new();
+
+    for path in cache_files(&root) {
+        let Ok(metadata) = std::fs::metadata(&path) else {
+            continue;
+        };
+        let entry = CacheEntry {
+            path,
+            size: metadata.len(),
+            modified: metadata.modified().unwrap_or(now),
+        };
+        let expired = max_age.is_some_and(|max_age| now.duration_since(entry.modified).unwrap_or_default() > max_age);
+        if !(expired || is_orphaned(&entry.path)) || !remove(&entry, &mut result) {
+            entries.push(entry);
+        }
+    }
+
+    if let Some(capacity) = policy.max_size_mb.map(|mb| (mb * MB) as u64) {
+        // Oldest thumbnails go first
+        entries.sort_by_key(|entry| entry.modified);
+        let mut total: u64 = entries.iter().map(|entry| entry.size).sum();
+        entries.retain(|entry| {
+            if total <= capacity || !remove(entry, &mut result) {
+                return true;
+            }
+            total = total.saturating_sub(entry.size);
+            false
+        });
+    }
+
+    result.remaining_count = entries.len() as u64;
+    result.remaining_size = entries.iter().map(|entry| entry.size).sum();
+    Ok(result)
+}
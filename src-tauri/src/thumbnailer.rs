@@ -0,0 +1,182 @@
+use gtk::glib::{self, KeyFile, KeyFileFlags};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+use url::Url;
+
+const GROUP: &str = "Thumbnailer Entry";
+const EXTENSION: &str = "thumbnailer";
+const OUTPUT_NAME: &str = "thumbnail.png";
+// Thumbnailers that hang on a broken file are killed after this
+const TIMEOUT: Duration = Duration::from_secs(30);
+const WAIT_INTERVAL: Duration = Duration::from_millis(50);
+
+static THUMBNAILERS: LazyLock<Vec<Thumbnailer>> = LazyLock::new(load);
+
+// An entry of /usr/share/thumbnailers as defined by the thumbnail spec
+#[derive(Debug, Clone)]
+pub struct Thumbnailer {
+    exec: String,
+    mime_types: Vec<String>,
+}
+
+impl Thumbnailer {
+    // Runs the thumbnailer and returns the image it wrote
+    pub fn run(&self, url: &Url, input: &Path, size: i32) -> Result<Vec<u8>, String> {
+        // The output goes to a directory only this user can enter, which is removed when dropped
+        let directory = tempfile::Builder::new().prefix("explite-thumbnailer-").tempdir().map_err(|e| e.to_string())?;
+        let output = directory.path().join(OUTPUT_NAME);
+        let args = self.args(url, input, &output, size)?;
+        let (program, args) = args.split_first().ok_or("Thumbnailer has no command")?;
+
+        let mut child = Command::new(program).args(args).spawn().map_err(|e| e.to_string())?;
+        let deadline = Instant::now() + TIMEOUT;
+        let status = loop {
+            match child.try_wait().map_err(|e| e.to_string())? {
+                Some(status) => break status,
+                None if Instant::now() >= deadline => {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(format!("Thumbnailer timed out after {} seconds", TIMEOUT.as_secs()));
+                }
+                None => std::thread::sleep(WAIT_INTERVAL),
+            }
+        };
+
+        if status.success() {
+            std::fs::read(&output).map_err(|e| e.to_string())
+        } else {
+            Err(format!("Thumbnailer exited with {}", status))
+        }
+    }
+
+    // Each argument is substituted separately so that paths with spaces stay one argument
+    fn args(&self, url: &Url, input: &Path, output: &Path, size: i32) -> Result<Vec<String>, String> {
+        let argv = glib::shell_parse_argv(&self.exec).map_err(|e| e.message().to_string())?;
+        Ok(argv
+            .iter()
+            .map(|arg| {
+                let arg = arg.to_string_lossy();
+                let mut result = String::new();
+                let mut chars = arg.chars();
+                while let Some(c) = chars.next() {
+                    if c != '%' {
+                        result.push(c);
+                        continue;
+                    }
+                    match chars.next() {
+                        Some('u') => result.push_str(url.as_str()),
+                        Some('i') => result.push_str(&input.to_string_lossy()),
+                        Some('o') => result.push_str(&output.to_string_lossy()),
+                        Some('s') => result.push_str(&size.to_string()),
+                        Some('%') => result.push('%'),
+                        Some(other) => {
+                            result.push('%');
+                            result.push(other);
+                        }
+                        None => result.push('%'),
+                    }
+                }
+                result
+            })
+            .collect())
+    }
+}
+
+pub fn find(mime_type: &str) -> Option<&'static Thumbnailer> {
+    THUMBNAILERS.iter().find(|thumbnailer| thumbnailer.mime_types.iter().any(|supported| supported == mime_type))
+}
+
+pub fn mime_types() -> Vec<String> {
+    let mut mime_types: Vec<String> = THUMBNAILERS.iter().flat_map(|thumbnailer| thumbnailer.mime_types.clone()).collect();
+    mime_types.sort();
+    mime_types.dedup();
+    mime_types
+}
+
+// User entries come first so that they override the system ones with the same file name
+fn directories() -> Vec<PathBuf> {
+    let data_home = std::env::var("XDG_DATA_HOME").ok().filter(|dir| !dir.is_empty()).map(PathBuf::from).or_else(|| std::env::var("HOME").ok().map(|home| PathBuf::from(home).join(".local/share")));
+    let data_dirs = std::env::var("XDG_DATA_DIRS").ok().filter(|dirs| !dirs.is_empty()).unwrap_or("/usr/local/share:/usr/share".to_string());
+    data_home.into_iter().chain(data_dirs.split(':').map(PathBuf::from)).map(|dir| dir.join("thumbnailers")).collect()
+}
+
+fn load() -> Vec<Thumbnailer> {
+    let mut names = HashSet::new();
+    let mut thumbnailers = Vec::new();
+
+    for directory in directories() {
+        let Ok(entries) = std::fs::read_dir(&directory) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().is_none_or(|extension| extension != EXTENSION) || !names.insert(entry.file_name()) {
+                continue;
+            }
+            if let Some(thumbnailer) = parse(&path) {
+                thumbnailers.push(thumbnailer);
+            }
+        }
+    }
+
+    thumbnailers
+}
+
+fn parse(path: &Path) -> Option<Thumbnailer> {
+    let key_file = KeyFile::new();
+    key_file.load_from_file(path, KeyFileFlags::NONE).ok()?;
+
+    // Entries whose program is not installed are ignored
+    if let Ok(try_exec) = key_file.string(GROUP, "TryExec") {
+        glib::find_program_in_path(try_exec.as_str())?;
+    }
+
+    let exec = key_file.string(GROUP, "Exec").ok()?.to_string();
+    let mime_types = key_file.string_list(GROUP, "MimeType").ok()?.iter().map(|mime_type| mime_type.to_string()).collect();
+
+    Some(Thumbnailer {
+        exec,
+        mime_types,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn thumbnailer(exec: &str) -> Thumbnailer {
+        Thumbnailer {
+            exec: exec.to_string(),
+            mime_types: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn paths_with_spaces_stay_one_argument() {
+        let input = Path::new("/home/user/My Videos/a b.mp4");
+        let url = Url::from_file_path(input).unwrap();
+        let args = thumbnailer("ffmpegthumbnailer -i %i -o %o -s %s").args(&url, input, Path::new("/tmp/out dir/thumbnail.png"), 256).unwrap();
+        assert_eq!(args, ["ffmpegthumbnailer", "-i", "/home/user/My Videos/a b.mp4", "-o", "/tmp/out dir/thumbnail.png", "-s", "256"]);
+    }
+
+    #[test]
+    fn percent_signs_are_substituted_once() {
+        let input = Path::new("/home/user/100%s.png");
+        let url = Url::from_file_path(input).unwrap();
+        let args = thumbnailer("thumbnail --uri=%u --literal=%% --unknown=%x --trailing=%").args(&url, input, Path::new("/tmp/out.png"), 128).unwrap();
+        assert_eq!(args, ["thumbnail", "--uri=file:///home/user/100%25s.png", "--literal=%", "--unknown=%x", "--trailing=%"]);
+    }
+
+    #[test]
+    fn input_is_not_substituted_again() {
+        let input = Path::new("/home/user/%o.png");
+        let url = Url::from_file_path(input).unwrap();
+        let args = thumbnailer("thumbnail '%i' %o").args(&url, input, Path::new("/tmp/out.png"), 128).unwrap();
+        assert_eq!(args, ["thumbnail", "/home/user/%o.png", "/tmp/out.png"]);
+    }
+}
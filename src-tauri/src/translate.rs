@@ -31,6 +31,7 @@ static JA: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
         ("Property", "プロパティ"),
         ("Refresh", "再読み込み"),
         ("Undelete", "元に戻す"),
+        ("RestoreTo", "指定した場所に戻す"),
         ("DeleteFromRecycleBin", "完全に削除する"),
         ("EmptyRecycleBin", "ゴミ箱を空にする"),
         ("CopyFullpath", "パスをコピー"),
@@ -45,6 +46,11 @@ static JA: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
         ("Trash", "削除"),
         ("AddToFavorite", "ピン留めする"),
         ("OpenInNewWindow", "新しいウィンドウで開く"),
+        ("OpenInNewTab", "新しいタブで開く"),
+        ("DuplicateTab", "タブを複製"),
+        ("CloseTab", "タブを閉じる"),
+        ("ReopenClosedTab", "閉じたタブを開き直す"),
+        ("MoveTabToNewWindow", "タブを新しいウィンドウに移動"),
     ])
 });
 static EN: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
@@ -62,6 +68,7 @@ static EN: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
         ("Property", "Property"),
         ("Refresh", "Refresh"),
         ("Undelete", "Undelete"),
+        ("RestoreTo", "Restore to..."),
         ("DeleteFromRecycleBin", "Delete"),
         ("EmptyRecycleBin", "Empty RecycleBin"),
         ("CopyFullpath", "Copy Path"),
@@ -76,5 +83,10 @@ static EN: LazyLock<HashMap<&str, &str>> = LazyLock::new(|| {
         ("Trash", "Trash"),
         ("AddToFavorite", "Pin"),
         ("OpenInNewWindow", "Open New Window"),
+        ("OpenInNewTab", "Open in New Tab"),
+        ("DuplicateTab", "Duplicate Tab"),
+        ("CloseTab", "Close Tab"),
+        ("ReopenClosedTab", "Reopen Closed Tab"),
+        ("MoveTabToNewWindow", "Move Tab to New Window"),
     ])
 });
@@ -0,0 +1,149 @@
+use crate::xdg_trash::{self, TrashDirectory, TrashEntry};
+use serde::{Deserialize, Serialize};
+use std::{
+    ffi::CString,
+    os::unix::ffi::OsStrExt,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tauri::Manager;
+
+const DAY_MS: f64 = 24.0 * 60.0 * 60.0 * 1000.0;
+const GB: f64 = 1024.0 * 1024.0 * 1024.0;
+const MB: f64 = 1024.0 * 1024.0;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrashPolicy {
+    max_age_days: Option<u32>,
+    max_size_gb: Option<f64>,
+    max_percent: Option<f64>,
+    prompt_size_mb: Option<f64>,
+}
+
+pub struct TrashPolicyState(Mutex<TrashPolicy>);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashUsage {
+    mount_point: String,
+    trash_path: String,
+    item_count: u64,
+    size: u64,
+    volume_size: u64,
+}
+
+pub fn setup(app: &tauri::App) {
+    app.manage(TrashPolicyState(Mutex::new(TrashPolicy::default())));
+}
+
+pub fn set_policy(app: &tauri::AppHandle, policy: TrashPolicy) {
+    *app.state::<TrashPolicyState>().0.lock().unwrap() = policy;
+    run_in_background(app);
+}
+
+pub fn run_in_background(app: &tauri::AppHandle) {
+    let policy = app.state::<TrashPolicyState>().0.lock().unwrap().clone();
+    std::thread::spawn(move || {
+        if let Err(e) = housekeep(&policy) {
+            eprintln!("Trash housekeeping error: {:?}", e);
+        }
+    });
+}
+
+fn now_ms() -> f64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as f64).unwrap_or_default()
+}
+
+pub fn volume_size(path: &Path) -> u64 {
+    let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+        return 0;
+    };
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } == 0 {
+        stat.f_blocks as u64 * stat.f_frsize as u64
+    } else {
+        0
+    }
+}
+
+fn mount_point_of(trash: &TrashDirectory) -> PathBuf {
+    trash.topdir.clone().unwrap_or_else(|| xdg_trash::topdir(&trash.root).unwrap_or(trash.root.clone()))
+}
+
+fn housekeep_directory(policy: &TrashPolicy, trash: &TrashDirectory) -> Result<(), String> {
+    let entries = xdg_trash::read_entries(trash);
+    let sizes = xdg_trash::entry_sizes(trash, &entries);
+    let mut entries: Vec<(TrashEntry, u64)> = entries.into_iter().zip(sizes).collect();
+
+    if let Some(days) = policy.max_age_days {
+        let limit = now_ms() - days as f64 * DAY_MS;
+        for (entry, _) in entries.iter().filter(|(entry, _)| entry.deleted_date_ms < limit) {
+            xdg_trash::purge(entry)?;
+        }
+        entries.retain(|(entry, _)| entry.deleted_date_ms >= limit);
+    }
+
+    let by_size = policy.max_size_gb.map(|gb| (gb * GB) as u64);
+    let by_percent = policy.max_percent.map(|percent| (volume_size(&mount_point_of(trash)) as f64 * percent / 100.0) as u64);
+    let capacity = match (by_size, by_percent) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    };
+
+    if let Some(capacity) = capacity {
+        // Oldest items go first
+        entries.sort_by(|(a, _), (b, _)| a.deleted_date_ms.total_cmp(&b.deleted_date_ms));
+        let mut total: u64 = entries.iter().map(|(_, size)| size).sum();
+        for (entry, size) in &entries {
+            if total <= capacity {
+                break;
+            }
+            xdg_trash::purge(entry)?;
+            total = total.saturating_sub(*size);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn housekeep(policy: &TrashPolicy) -> Result<(), String> {
+    for trash in xdg_trash::trash_directories() {
+        housekeep_directory(policy, &trash)?;
+    }
+    Ok(())
+}
+
+// Returns the paths that exceed the size threshold and should be deleted permanently instead
+pub fn oversized(app: &tauri::AppHandle, paths: &[String]) -> Vec<String> {
+    let policy = app.state::<TrashPolicyState>().0.lock().unwrap().clone();
+    let Some(threshold) = policy.prompt_size_mb.map(|mb| (mb * MB) as u64) else {
+        return Vec::new();
+    };
+    paths.iter().filter(|path| xdg_trash::directory_size(Path::new(path)) > threshold).cloned().collect()
+}
+
+pub fn usage() -> Vec<TrashUsage> {
+    xdg_trash::trash_directories()
+        .iter()
+        .map(|trash| {
+            let entries = xdg_trash::read_entries(trash);
+            let mount_point = mount_point_of(trash);
+            TrashUsage {
+                mount_point: mount_point.to_string_lossy().to_string(),
+                trash_path: trash.root.to_string_lossy().to_string(),
+                item_count: entries.len() as u64,
+                size: xdg_trash::entry_sizes(trash, &entries).iter().sum(),
+                volume_size: volume_size(&mount_point),
+            }
+        })
+        .collect()
+}
+
+pub fn empty_volume(mount_point: &str) -> Result<(), String> {
+    for trash in xdg_trash::trash_directories().iter().filter(|trash| mount_point_of(trash) == Path::new(mount_point)) {
+        for entry in xdg_trash::read_entries(trash) {
+            xdg_trash::purge(&entry)?;
+        }
+    }
+    Ok(())
+}
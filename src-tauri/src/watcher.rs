@@ -1,20 +1,44 @@
+use crate::{
+    mount::{self, MountInfo},
+    remote::{FileAttribute, LocalProvider, Provider},
+};
 use notify_debouncer_full::{
     new_debouncer, new_debouncer_opt,
     notify::{
+        self,
         event::{ModifyKind, RenameMode},
-        Config, EventKind, RecursiveMode,
+        Config, EventKind, PollWatcher, RecommendedWatcher, RecursiveMode,
     },
-    DebouncedEvent,
+    DebounceEventResult, DebouncedEvent, Debouncer, RecommendedCache,
 };
 use serde::{Deserialize, Serialize};
 use smol::channel::{bounded, Receiver, Sender};
-use std::time::Duration;
-use tauri::Emitter;
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+use tauri::{Emitter, Manager};
 
 const WATCH_EVENT_NAME: &str = "watch_event";
 const CREATE: &str = "Create";
 const REMOVE: &str = "Remove";
 const RENAME: &str = "Rename";
+const LIMITED: &str = "Limited";
+const UNAVAILABLE: &str = "Unavailable";
+const RESCAN: &str = "Rescan";
+const ROOT_MOVED: &str = "RootMoved";
+const ROOT_REMOVED: &str = "RootRemoved";
+const EVENT_QUEUE_SIZE: usize = 256;
+const DEFAULT_POLL_INTERVAL_SECS: u64 = 2;
+const MODIFY: &str = "Modify";
+const METADATA: &str = "Metadata";
+// Files being written fire continuously, so modifications are reported at most this often
+const MODIFY_DEBOUNCE: Duration = Duration::from_millis(500);
 
 pub struct WatchTx(pub Sender<WatcherCommand>);
 
@@ -23,100 +47,623 @@ struct WatchEvent {
     operation: String,
     to_paths: Vec<String>,
     from_paths: Vec<String>,
+    view: String,
+    tab: u32,
+    attributes: Vec<FileAttribute>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchRequest {
+    pub path: String,
+    #[serde(default)]
+    pub view: String,
+    // Tab of the window that owns the view
+    #[serde(default)]
+    pub tab: u32,
+    #[serde(default)]
+    pub recursive: bool,
+    pub depth: Option<usize>,
+    #[serde(default)]
+    pub excludes: Vec<String>,
 }
 
 pub enum WatcherCommand {
-    Watch(String, bool),
-    Unwatch(String, bool),
+    Watch(String, WatchRequest),
+    Unwatch(String, WatchRequest),
+    ForgetWindow(String),
+    SetPollIntervals(HashMap<String, u64>),
+    Refresh,
 }
 
 enum Watcher<L, R> {
     Normal(L),
     PollWatcher(R),
+    Flush,
+}
+
+struct Subscription {
+    request: WatchRequest,
+    root: PathBuf,
+    mount: MountInfo,
+    // Set when the inotify watch limit was hit and only the root is watched
+    limited: bool,
+    // Set when changes cannot be watched at all, e.g. polling is disabled for the mount
+    unavailable: bool,
+    // Window label -> number of watch requests from that window
+    windows: HashMap<String, usize>,
+}
+
+type Subscriptions = Arc<Mutex<Vec<Subscription>>>;
+
+// (window label, view, tab)
+type Target = (String, String, u32);
+
+// (target, path) -> whether the content changed rather than only metadata
+type PendingModifies = HashMap<(Target, PathBuf), bool>;
+
+// Events from one debounce window are sent to each app window as a single payload
+type Batches = HashMap<Target, Vec<WatchEvent>>;
+
+fn matches_pattern(name: &str, pattern: &str) -> bool {
+    match pattern.strip_prefix('*') {
+        Some(suffix) => name.ends_with(suffix),
+        None => name == pattern,
+    }
+}
+
+impl Subscription {
+    fn new(request: WatchRequest) -> Self {
+        let root = PathBuf::from(&request.path);
+        Self {
+            mount: mount::detect(&root),
+            root,
+            request,
+            limited: false,
+            unavailable: false,
+            windows: HashMap::new(),
+        }
+    }
+
+    fn targets(&self) -> impl Iterator<Item = Target> + '_ {
+        self.windows.keys().map(|label| (label.clone(), self.request.view.clone(), self.request.tab))
+    }
+
+    fn is_same(&self, request: &WatchRequest) -> bool {
+        self.request.path == request.path && self.request.view == request.view && self.request.tab == request.tab
+    }
+
+    fn is_recursive(&self) -> bool {
+        self.request.recursive && !self.limited
+    }
+
+    fn is_excluded(&self, path: &Path) -> bool {
+        path.components().any(|component| self.request.excludes.iter().any(|pattern| matches_pattern(&component.as_os_str().to_string_lossy(), pattern)))
+    }
+
+    fn covers(&self, path: &Path) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return false;
+        };
+        if self.is_excluded(relative) {
+            return false;
+        }
+        // Direct children have depth 1
+        let depth = relative.components().count();
+        if !self.is_recursive() {
+            return depth <= 1;
+        }
+        self.request.depth.map(|max| depth <= max + 1).unwrap_or(true)
+    }
+
+    fn is_depth_limited(&self) -> bool {
+        self.is_recursive() && self.request.depth.is_some()
+    }
+
+    fn directories(&self) -> Vec<(PathBuf, RecursiveMode)> {
+        if !self.is_recursive() {
+            return vec![(self.root.clone(), RecursiveMode::NonRecursive)];
+        }
+        match self.request.depth {
+            // Excludes cannot be applied to a recursive watch, so events are filtered in covers
+            None => vec![(self.root.clone(), RecursiveMode::Recursive)],
+            Some(max) => {
+                let mut directories = Vec::new();
+                self.walk(&self.root, max, &mut directories);
+                directories
+            }
+        }
+    }
+
+    fn walk(&self, directory: &Path, remaining: usize, directories: &mut Vec<(PathBuf, RecursiveMode)>) {
+        directories.push((directory.to_path_buf(), RecursiveMode::NonRecursive));
+        if remaining == 0 {
+            return;
+        }
+        let Ok(entries) = std::fs::read_dir(directory) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let is_directory = entry.file_type().map(|file_type| file_type.is_dir()).unwrap_or_default();
+            if is_directory && !self.request.excludes.iter().any(|pattern| matches_pattern(&entry.file_name().to_string_lossy(), pattern)) {
+                self.walk(&entry.path(), remaining - 1, directories);
+            }
+        }
+    }
+}
+
+struct Watches {
+    watcher: Debouncer<RecommendedWatcher, RecommendedCache>,
+    // One poll watcher per interval in use
+    poll_watchers: HashMap<Duration, Debouncer<PollWatcher, RecommendedCache>>,
+    poll_tx: Sender<DebounceEventResult>,
+    overflowed: Arc<AtomicBool>,
+    // Mount point -> poll interval in seconds, 0 disables polling
+    intervals: HashMap<PathBuf, u64>,
+    // Directory -> (mode, poll interval)
+    active: HashMap<PathBuf, (RecursiveMode, Option<Duration>)>,
+}
+
+#[derive(Default)]
+struct SyncResult {
+    limited: HashSet<usize>,
+    unavailable: HashSet<usize>,
+}
+
+impl Watches {
+    fn watch(&mut self, path: &Path, mode: RecursiveMode, poll: Option<Duration>) -> Result<(), notify::Error> {
+        let Some(interval) = poll else {
+            return self.watcher.watch(path, mode);
+        };
+
+        if !self.poll_watchers.contains_key(&interval) {
+            let poll_tx = self.poll_tx.clone();
+            let overflowed = self.overflowed.clone();
+            let poll_watcher = new_debouncer_opt(
+                Duration::from_millis(100),
+                None,
+                move |res| queue(&poll_tx, &overflowed, res),
+                RecommendedCache::new(),
+                Config::default().with_poll_interval(interval).with_follow_symlinks(false),
+            )?;
+            self.poll_watchers.insert(interval, poll_watcher);
+        }
+        self.poll_watchers.get_mut(&interval).unwrap().watch(path, mode)
+    }
+
+    fn unwatch(&mut self, path: &Path, poll: Option<Duration>) {
+        match poll {
+            Some(interval) => {
+                if let Some(poll_watcher) = self.poll_watchers.get_mut(&interval) {
+                    let _ = poll_watcher.unwatch(path);
+                }
+            }
+            None => {
+                let _ = self.watcher.unwatch(path);
+            }
+        }
+    }
+
+    // None means the mount is local, Err means polling is disabled for it
+    fn poll_interval(&self, subscription: &Subscription) -> Result<Option<Duration>, ()> {
+        if !subscription.mount.remote {
+            return Ok(None);
+        }
+        match self.intervals.get(&subscription.mount.mount_point).copied().unwrap_or(DEFAULT_POLL_INTERVAL_SECS) {
+            0 => Err(()),
+            secs => Ok(Some(Duration::from_secs(secs))),
+        }
+    }
+
+    // Brings the active watches in line with what the subscriptions need
+    fn sync(&mut self, subscriptions: &[Subscription]) -> SyncResult {
+        let mut result = SyncResult::default();
+        let mut desired: HashMap<PathBuf, (RecursiveMode, Option<Duration>)> = HashMap::new();
+        let mut owners: HashMap<PathBuf, Vec<usize>> = HashMap::new();
+        for (index, subscription) in subscriptions.iter().enumerate() {
+            // URIs of remote providers have nothing to watch and are reloaded by hand, so they are not reported
+            if subscription.request.path.contains("://") {
+                continue;
+            }
+            let Ok(poll) = self.poll_interval(subscription) else {
+                result.unavailable.insert(index);
+                continue;
+            };
+            for (directory, mode) in subscription.directories() {
+                let entry = desired.entry(directory.clone()).or_insert((mode, poll));
+                if mode == RecursiveMode::Recursive {
+                    entry.0 = RecursiveMode::Recursive;
+                }
+                owners.entry(directory).or_default().push(index);
+            }
+            // The parent sees the root itself being renamed or removed
+            if let Some(parent) = subscription.root.parent() {
+                desired.entry(parent.to_path_buf()).or_insert((RecursiveMode::NonRecursive, poll));
+            }
+        }
+
+        let stale: Vec<(PathBuf, Option<Duration>)> = self.active.iter().filter(|(path, watch)| desired.get(*path) != Some(*watch)).map(|(path, (_, poll))| (path.clone(), *poll)).collect();
+        for (path, poll) in stale {
+            self.unwatch(&path, poll);
+            self.active.remove(&path);
+        }
+
+        for (path, (mode, poll)) in desired {
+            if self.active.contains_key(&path) {
+                continue;
+            }
+            let owned_by = owners.get(&path).cloned().unwrap_or_default();
+            match self.watch(&path, mode, poll) {
+                Ok(_) => {
+                    self.active.insert(path, (mode, poll));
+                }
+                Err(e) if matches!(e.kind, notify::ErrorKind::MaxFilesWatch) => {
+                    // A partially added recursive watch must be dropped before falling back
+                    self.unwatch(&path, poll);
+                    result.limited.extend(owned_by.into_iter().filter(|index| subscriptions[*index].is_recursive()));
+                }
+                Err(e) => {
+                    eprintln!("Watcher error: {:?}", e);
+                    if owned_by.first().is_some_and(|index| path == subscriptions[*index].root) {
+                        result.unavailable.extend(owned_by);
+                    }
+                }
+            }
+        }
+
+        // Drop poll watchers no longer in use so their threads stop
+        let intervals: HashSet<Duration> = self.active.values().filter_map(|(_, poll)| *poll).collect();
+        self.poll_watchers.retain(|interval, _| intervals.contains(interval));
+
+        result
+    }
+}
+
+// A full queue means events were lost, so the views must reload instead
+fn queue(tx: &Sender<DebounceEventResult>, overflowed: &AtomicBool, result: DebounceEventResult) {
+    if tx.try_send(result).is_err() {
+        overflowed.store(true, Ordering::Relaxed);
+    }
+}
+
+fn notice(subscription: &Subscription, operation: &str, view: String) -> WatchEvent {
+    WatchEvent {
+        operation: operation.to_string(),
+        to_paths: vec![subscription.request.path.clone()],
+        from_paths: Vec::new(),
+        view,
+        tab: subscription.request.tab,
+        attributes: Vec::new(),
+    }
+}
+
+fn emit_notice(app_handle: &tauri::AppHandle, subscription: &Subscription, operation: &str) {
+    for (label, view, _) in subscription.targets() {
+        let _ = app_handle.emit_to(label, WATCH_EVENT_NAME, vec![notice(subscription, operation, view)]);
+    }
+}
+
+fn emit_batches(app_handle: &tauri::AppHandle, batches: Batches) {
+    let mut windows: HashMap<String, Vec<WatchEvent>> = HashMap::new();
+    for ((label, _, _), events) in batches {
+        windows.entry(label).or_default().extend(events);
+    }
+    for (label, events) in windows {
+        let _ = app_handle.emit_to(label, WATCH_EVENT_NAME, events);
+    }
+}
+
+fn rescan_all(subscriptions: &Subscriptions, batches: &mut Batches) {
+    for subscription in subscriptions.lock().unwrap().iter() {
+        for target in subscription.targets() {
+            let event = notice(subscription, RESCAN, target.1.clone());
+            batches.entry(target).or_default().push(event);
+        }
+    }
+}
+
+fn flush_modifies(pending: &mut PendingModifies, batches: &mut Batches) {
+    let mut grouped: HashMap<(Target, bool), Vec<PathBuf>> = HashMap::new();
+    for ((target, path), content) in pending.drain() {
+        grouped.entry((target, content)).or_default().push(path);
+    }
+
+    for ((target, content), paths) in grouped {
+        // Removed files are reported by their own event
+        let (to_paths, attributes): (Vec<String>, Vec<FileAttribute>) = paths
+            .iter()
+            .filter_map(|path| {
+                let path = path.to_string_lossy().to_string();
+                LocalProvider.stat(&path).ok().map(|attribute| (path, attribute))
+            })
+            .unzip();
+
+        if to_paths.is_empty() {
+            continue;
+        }
+
+        let (view, tab) = (target.1.clone(), target.2);
+        batches.entry(target).or_default().push(WatchEvent {
+            operation: if content {
+                MODIFY.to_string()
+            } else {
+                METADATA.to_string()
+            },
+            to_paths,
+            from_paths: Vec::new(),
+            view,
+            tab,
+            attributes,
+        });
+    }
 }
 
 pub fn spwan_watcher(app_handle: &tauri::AppHandle, cmd_rx: Receiver<WatcherCommand>) -> Result<(), String> {
-    let (tx, rx) = bounded(1);
-    let (tx_poll, rx_poll) = bounded(1);
-
-    let mut watcher = new_debouncer(Duration::from_millis(100), None, move |res| tx.try_send(res).unwrap_or_default()).map_err(|e| e.to_string())?;
-    let mut poll_watcher: notify_debouncer_full::Debouncer<notify_debouncer_full::notify::PollWatcher, _> = new_debouncer_opt(
-        Duration::from_millis(100),
-        None,
-        move |res| tx_poll.try_send(res).unwrap_or_default(),
-        notify_debouncer_full::RecommendedCache::new(),
-        Config::default().with_poll_interval(Duration::from_secs(2)).with_follow_symlinks(false),
-    )
-    .map_err(|e| e.to_string())?;
+    let (tx, rx) = bounded(EVENT_QUEUE_SIZE);
+    let (tx_poll, rx_poll) = bounded(EVENT_QUEUE_SIZE);
+    let overflowed = Arc::new(AtomicBool::new(false));
+
+    let watcher_overflowed = overflowed.clone();
+    let watcher = new_debouncer(Duration::from_millis(100), None, move |res| queue(&tx, &watcher_overflowed, res)).map_err(|e| e.to_string())?;
     let app_handle = app_handle.clone();
+    let subscriptions: Subscriptions = Arc::new(Mutex::new(Vec::new()));
+    let command_subscriptions = subscriptions.clone();
+    let command_app_handle = app_handle.clone();
 
     tauri::async_runtime::spawn(async move {
+        let mut watches = Watches {
+            watcher,
+            poll_watchers: HashMap::new(),
+            poll_tx: tx_poll,
+            overflowed: overflowed.clone(),
+            intervals: HashMap::new(),
+            active: HashMap::new(),
+        };
+
         while let Ok(cmd) = cmd_rx.recv().await {
+            let mut subscriptions = command_subscriptions.lock().unwrap();
             match cmd {
-                WatcherCommand::Watch(path, network) => {
-                    if network {
-                        let _ = poll_watcher.watch(path, RecursiveMode::NonRecursive);
-                    } else {
-                        let _ = watcher.watch(path, RecursiveMode::NonRecursive);
+                WatcherCommand::Watch(label, request) => {
+                    let index = match subscriptions.iter().position(|subscription| subscription.is_same(&request)) {
+                        Some(index) => index,
+                        None => {
+                            subscriptions.push(Subscription::new(request));
+                            subscriptions.len() - 1
+                        }
+                    };
+                    *subscriptions[index].windows.entry(label).or_default() += 1;
+                }
+                WatcherCommand::Unwatch(label, request) => {
+                    if let Some(subscription) = subscriptions.iter_mut().find(|subscription| subscription.is_same(&request)) {
+                        if let Some(count) = subscription.windows.get_mut(&label) {
+                            *count -= 1;
+                            if *count == 0 {
+                                subscription.windows.remove(&label);
+                            }
+                        }
                     }
                 }
-                WatcherCommand::Unwatch(path, network) => {
-                    if network {
-                        let _ = poll_watcher.unwatch(path);
-                    } else {
-                        let _ = watcher.unwatch(path);
+                WatcherCommand::ForgetWindow(label) => {
+                    for subscription in subscriptions.iter_mut() {
+                        subscription.windows.remove(&label);
                     }
                 }
+                WatcherCommand::SetPollIntervals(intervals) => {
+                    watches.intervals = intervals.into_iter().map(|(mount_point, secs)| (PathBuf::from(mount_point), secs)).collect();
+                }
+                WatcherCommand::Refresh => {}
+            }
+
+            // Only paths some window still needs stay watched
+            subscriptions.retain(|subscription| !subscription.windows.is_empty());
+
+            let mut result = watches.sync(&subscriptions);
+            if !result.limited.is_empty() {
+                // Fall back to watching only the root of subscriptions that hit the limit
+                for index in &result.limited {
+                    subscriptions[*index].limited = true;
+                    emit_notice(&command_app_handle, &subscriptions[*index], LIMITED);
+                }
+                result = watches.sync(&subscriptions);
+            }
+
+            for (index, subscription) in subscriptions.iter_mut().enumerate() {
+                let unavailable = result.unavailable.contains(&index);
+                if unavailable && !subscription.unavailable {
+                    emit_notice(&command_app_handle, subscription, UNAVAILABLE);
+                }
+                subscription.unavailable = unavailable;
             }
         }
     });
 
     tauri::async_runtime::spawn(async move {
+        let mut pending = PendingModifies::new();
+        let mut last_flush = Instant::now();
+        // Root renamed away whose new name has not arrived yet, and whether its From half was seen
+        let mut renamed_root: Option<(PathBuf, bool)> = None;
+        // A From half was emitted and its To half has not arrived yet
+        let mut rename_from = false;
+
         loop {
-            let event = smol::future::race(async { Watcher::Normal(rx.recv().await) }, async { Watcher::PollWatcher(rx_poll.recv().await) }).await;
-            match event {
-                Watcher::Normal(result) => {
-                    if let Ok(event_result) = result {
-                        match event_result {
-                            Ok(events) => {
-                                for event in events {
-                                    handle_event(&app_handle, event);
-                                }
-                            }
-                            Err(errors) => {
-                                for error in errors {
-                                    eprintln!("Watcher error: {:?}", error);
-                                }
-                            }
+            let event = smol::future::race(smol::future::race(async { Watcher::Normal(rx.recv().await) }, async { Watcher::PollWatcher(rx_poll.recv().await) }), async {
+                smol::Timer::after(MODIFY_DEBOUNCE).await;
+                Watcher::Flush
+            })
+            .await;
+
+            let result = match event {
+                Watcher::Normal(result) => result,
+                Watcher::PollWatcher(result) => result,
+                Watcher::Flush => Ok(Ok(Vec::new())),
+            };
+
+            let mut batches = Batches::new();
+
+            if let Ok(event_result) = result {
+                match event_result {
+                    Ok(events) => {
+                        for event in events {
+                            track_root(&subscriptions, &mut renamed_root, &mut batches, &event);
+                            handle_event(&app_handle, &subscriptions, &mut pending, &mut rename_from, &mut batches, event).await;
                         }
                     }
-                }
-
-                Watcher::PollWatcher(result) => {
-                    if let Ok(event_result) = result {
-                        match event_result {
-                            Ok(events) => {
-                                for event in events {
-                                    handle_event(&app_handle, event);
-                                }
-                            }
-                            Err(errors) => {
-                                for error in errors {
-                                    eprintln!("Watcher error: {:?}", error);
-                                }
-                            }
+                    Err(errors) => {
+                        for error in errors {
+                            eprintln!("Watcher error: {:?}", error);
                         }
                     }
                 }
             }
+
+            // No new name arrived within the debounce window, so the root was moved out of sight
+            if let Some((root, _)) = renamed_root.take() {
+                root_removed(&subscriptions, &root, &mut batches);
+            }
+
+            if overflowed.swap(false, Ordering::Relaxed) {
+                rescan_all(&subscriptions, &mut batches);
+            }
+
+            if !pending.is_empty() && last_flush.elapsed() >= MODIFY_DEBOUNCE {
+                flush_modifies(&mut pending, &mut batches);
+                last_flush = Instant::now();
+            }
+
+            emit_batches(&app_handle, batches);
         }
     });
 
     Ok(())
 }
 
-fn handle_event(app_handle: &tauri::AppHandle, event: DebouncedEvent) {
-    let event_type = get_event_type(event.kind);
+fn emit_root_change(subscriptions: &Subscriptions, root: &Path, operation: &str, to: &Path, batches: &mut Batches) {
+    for subscription in subscriptions.lock().unwrap().iter().filter(|subscription| subscription.root == root) {
+        for target in subscription.targets() {
+            let (view, tab) = (target.1.clone(), target.2);
+            batches.entry(target).or_default().push(WatchEvent {
+                operation: operation.to_string(),
+                to_paths: vec![to.to_string_lossy().to_string()],
+                from_paths: vec![root.to_string_lossy().to_string()],
+                view,
+                tab,
+                attributes: Vec::new(),
+            });
+        }
+    }
+}
+
+// Points the window to the nearest folder that still exists
+fn root_removed(subscriptions: &Subscriptions, root: &Path, batches: &mut Batches) {
+    // Both the root and its parent report the change
+    let root_path = root.to_string_lossy();
+    let reported = batches.values().flatten().any(|event| (event.operation == ROOT_MOVED || event.operation == ROOT_REMOVED) && event.from_paths.iter().any(|from| *from == root_path));
+    if reported || root.exists() {
+        return;
+    }
+    if let Some(ancestor) = root.ancestors().skip(1).find(|ancestor| ancestor.exists()) {
+        emit_root_change(subscriptions, root, ROOT_REMOVED, ancestor, batches);
+    }
+}
+
+fn is_root(subscriptions: &Subscriptions, path: &Path) -> bool {
+    subscriptions.lock().unwrap().iter().any(|subscription| subscription.root == path)
+}
+
+// Detects the watched folder itself being renamed or removed
+fn track_root(subscriptions: &Subscriptions, renamed_root: &mut Option<(PathBuf, bool)>, batches: &mut Batches, event: &DebouncedEvent) {
+    let Some(path) = event.paths.first() else {
+        return;
+    };
+
+    match event.kind {
+        EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if is_root(subscriptions, path) => {
+            if let Some(to) = event.paths.get(1) {
+                emit_root_change(subscriptions, path, ROOT_MOVED, to, batches);
+            }
+        }
+        // Only a To that completes the root's own From is its new name
+        EventKind::Modify(ModifyKind::Name(RenameMode::To)) => {
+            if let Some((root, true)) = renamed_root.as_ref() {
+                emit_root_change(subscriptions, root, ROOT_MOVED, path, batches);
+                *renamed_root = None;
+            }
+        }
+        EventKind::Modify(ModifyKind::Name(RenameMode::From)) if is_root(subscriptions, path) => {
+            *renamed_root = Some((path.clone(), true));
+        }
+        // IN_MOVE_SELF on the root is reported without the new name
+        EventKind::Modify(ModifyKind::Name(_)) if is_root(subscriptions, path) => {
+            if renamed_root.is_none() {
+                *renamed_root = Some((path.clone(), false));
+            }
+        }
+        EventKind::Remove(_) if is_root(subscriptions, path) => {
+            root_removed(subscriptions, path, batches);
+        }
+        _ => {}
+    }
+}
+
+// Drops every subscription held by a closed window
+pub fn forget_window(app_handle: &tauri::AppHandle, label: &str) {
+    if let Some(tx) = app_handle.try_state::<WatchTx>() {
+        // This runs on the main thread, so a full queue is waited for on the async runtime instead
+        if let Err(smol::channel::TrySendError::Full(command)) = tx.0.try_send(WatcherCommand::ForgetWindow(label.to_string())) {
+            let tx = tx.0.clone();
+            tauri::async_runtime::spawn(async move {
+                let _ = tx.send(command).await;
+            });
+        }
+    }
+}
+
+async fn handle_event(app_handle: &tauri::AppHandle, subscriptions: &Subscriptions, pending: &mut PendingModifies, rename_from: &mut bool, batches: &mut Batches, event: DebouncedEvent) {
+    // The kernel queue overflowed and the backend cannot tell what changed
+    if event.need_rescan() {
+        let paths: Vec<String> = event.paths.iter().map(|p| p.to_string_lossy().to_string()).collect();
+        let covered: Vec<Target> =
+            subscriptions.lock().unwrap().iter().filter(|subscription| event.paths.iter().any(|path| subscription.covers(path))).flat_map(|subscription| subscription.targets()).collect();
+        if covered.is_empty() {
+            rescan_all(subscriptions, batches);
+        }
+        for target in covered {
+            let (view, tab) = (target.1.clone(), target.2);
+            batches.entry(target).or_default().push(WatchEvent {
+                operation: RESCAN.to_string(),
+                to_paths: paths.clone(),
+                from_paths: Vec::new(),
+                view,
+                tab,
+                attributes: Vec::new(),
+            });
+        }
+        return;
+    }
+
+    let event_type = match get_event_type(event.kind) {
+        EventType::RenameFrom => {
+            *rename_from = true;
+            EventType::RenameFrom
+        }
+        // A To without a From has nothing to rename, so the target simply appeared
+        EventType::RenameTo if !std::mem::take(rename_from) => EventType::Create,
+        event_type => event_type,
+    };
+
+    let (targets, needs_refresh) = {
+        let subscriptions = subscriptions.lock().unwrap();
+        let matched: Vec<&Subscription> = subscriptions.iter().filter(|subscription| event.paths.iter().any(|path| subscription.covers(path))).collect();
+        let targets: HashSet<Target> = matched.iter().flat_map(|subscription| subscription.targets()).collect();
+        // New folders inside a depth limited subscription need their own watch
+        let needs_refresh = event_type != EventType::Remove && matched.iter().any(|subscription| subscription.is_depth_limited()) && event.paths.iter().any(|path| path.is_dir());
+        (targets, needs_refresh)
+    };
+
+    if needs_refresh {
+        if let Some(tx) = app_handle.try_state::<WatchTx>() {
+            let _ = tx.0.send(WatcherCommand::Refresh).await;
+        }
+    }
 
     match event_type {
         EventType::Create | EventType::Remove | EventType::Rename | EventType::RenameFrom | EventType::RenameTo => {
@@ -136,16 +683,25 @@ fn handle_event(app_handle: &tauri::AppHandle, event: DebouncedEvent) {
                 _ => (Vec::new(), paths),
             };
 
-            app_handle
-                .emit(
-                    WATCH_EVENT_NAME,
-                    WatchEvent {
-                        operation,
-                        to_paths: to,
-                        from_paths: from,
-                    },
-                )
-                .unwrap();
+            for target in targets {
+                let (view, tab) = (target.1.clone(), target.2);
+                batches.entry(target).or_default().push(WatchEvent {
+                    operation: operation.clone(),
+                    to_paths: to.clone(),
+                    from_paths: from.clone(),
+                    view,
+                    tab,
+                    attributes: Vec::new(),
+                });
+            }
+        }
+        EventType::ModifyAny | EventType::ModifyData | EventType::ModifyMetadata => {
+            let content = event_type != EventType::ModifyMetadata;
+            for target in targets {
+                for path in &event.paths {
+                    *pending.entry((target.clone(), path.clone())).or_default() |= content;
+                }
+            }
         }
         _ => {}
     }
@@ -155,6 +711,8 @@ fn handle_event(app_handle: &tauri::AppHandle, event: DebouncedEvent) {
 #[derive(PartialEq, Debug)]
 enum EventType {
     ModifyAny,
+    ModifyData,
+    ModifyMetadata,
     Remove,
     Create,
     None,
@@ -176,6 +734,8 @@ fn get_event_type(event_kind: EventKind) -> EventType {
     // Notify may not emit RenameMode::Both
     match event_kind {
         EventKind::Modify(ModifyKind::Any) => EventType::ModifyAny,
+        EventKind::Modify(ModifyKind::Data(_)) => EventType::ModifyData,
+        EventKind::Modify(ModifyKind::Metadata(_)) => EventType::ModifyMetadata,
         EventKind::Modify(ModifyKind::Name(RenameMode::From)) => EventType::RenameFrom,
         EventKind::Modify(ModifyKind::Name(RenameMode::To)) => EventType::RenameTo,
         EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => EventType::Rename,
@@ -185,6 +745,10 @@ fn get_event_type(event_kind: EventKind) -> EventType {
     #[cfg(target_os = "linux")]
     match event_kind {
         EventKind::Modify(ModifyKind::Any) => EventType::ModifyAny,
+        EventKind::Modify(ModifyKind::Data(_)) => EventType::ModifyData,
+        EventKind::Modify(ModifyKind::Metadata(_)) => EventType::ModifyMetadata,
+        // Written files are closed once the write is complete
+        EventKind::Access(notify::event::AccessKind::Close(notify::event::AccessMode::Write)) => EventType::ModifyData,
         EventKind::Modify(ModifyKind::Name(RenameMode::From)) => EventType::Remove,
         EventKind::Modify(ModifyKind::Name(RenameMode::To)) => EventType::Create,
         EventKind::Modify(ModifyKind::Name(RenameMode::Both)) => EventType::Rename,
@@ -0,0 +1,664 @@
+use crate::remote::{FileAttribute, Provider, TransferControl, TransferStatus};
+use gtk::glib;
+use percent_encoding::{percent_decode_str, percent_encode, utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs::{create_dir_all, set_permissions, OpenOptions, Permissions},
+    io::{Read, Write},
+    os::unix::{
+        ffi::{OsStrExt, OsStringExt},
+        fs::{MetadataExt, PermissionsExt},
+    },
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+const TRASH_INFO_EXTENSION: &str = "trashinfo";
+const DIRECTORY_SIZES: &str = "directorysizes";
+const DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+const DIRECTORY_PERM: u32 = 0o700;
+const STICKY_BIT: u32 = 0o1000;
+// Characters escaped in the Path key, following the URI rules the spec refers to
+const PATH_ENCODE_SET: &AsciiSet = &CONTROLS.add(b' ').add(b'"').add(b'#').add(b'%').add(b'<').add(b'>').add(b'?').add(b'[').add(b'\\').add(b']').add(b'^').add(b'`').add(b'{').add(b'|').add(b'}');
+
+// Housekeeping and trashing run on separate threads, so rewrites of directorysizes take turns
+static DIRECTORY_SIZES_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecycleBinDirent {
+    // Path of the .trashinfo file, which tells apart items trashed from the same path
+    pub trash_id: String,
+    pub name: String,
+    pub original_path: String,
+    pub deleted_date_ms: f64,
+    pub mime_type: String,
+    pub attributes: FileAttribute,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecycleBinItem {
+    pub original_path: String,
+    pub deleted_time_ms: f64,
+    #[serde(default)]
+    pub trash_id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrashDirectory {
+    // Directory holding files/ and info/
+    pub root: PathBuf,
+    // Base for relative Path keys. None for the home trash, which stores absolute paths
+    pub topdir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TrashEntry {
+    pub trash: TrashDirectory,
+    // File name inside files/ without the .trashinfo extension
+    pub id: String,
+    pub original_path: PathBuf,
+    pub deleted_date_ms: f64,
+}
+
+impl TrashEntry {
+    pub fn file(&self) -> PathBuf {
+        self.trash.root.join("files").join(&self.id)
+    }
+
+    fn info(&self) -> PathBuf {
+        self.trash.root.join("info").join(format!("{}.{}", self.id, TRASH_INFO_EXTENSION))
+    }
+}
+
+fn uid() -> u32 {
+    unsafe { libc::getuid() }
+}
+
+fn data_home() -> Option<PathBuf> {
+    if let Ok(data_home) = std::env::var("XDG_DATA_HOME") {
+        if !data_home.is_empty() {
+            return Some(PathBuf::from(data_home));
+        }
+    }
+    std::env::var("HOME").ok().map(|home| PathBuf::from(home).join(".local").join("share"))
+}
+
+fn home_trash() -> Result<TrashDirectory, String> {
+    let root = data_home().ok_or("Home trash directory not found".to_string())?.join("Trash");
+    Ok(TrashDirectory {
+        root,
+        topdir: None,
+    })
+}
+
+// Returns the mount point of the path by walking up until the device changes
+pub fn topdir(path: &Path) -> Option<PathBuf> {
+    let dev = std::fs::symlink_metadata(path).ok()?.dev();
+    let mut topdir = path.to_path_buf();
+    for ancestor in path.ancestors().skip(1) {
+        match std::fs::metadata(ancestor) {
+            Ok(metadata) if metadata.dev() == dev => topdir = ancestor.to_path_buf(),
+            _ => break,
+        }
+    }
+    Some(topdir)
+}
+
+// $topdir/.Trash/$uid is only valid when .Trash is a real directory with the sticky bit set
+fn shared_trash(topdir: &Path) -> Option<TrashDirectory> {
+    let admin = topdir.join(".Trash");
+    let metadata = std::fs::symlink_metadata(&admin).ok()?;
+    if !metadata.is_dir() || metadata.permissions().mode() & STICKY_BIT == 0 {
+        return None;
+    }
+    let root = admin.join(uid().to_string());
+    if !root.exists() {
+        create_dir_all(&root).ok()?;
+        set_permissions(&root, Permissions::from_mode(DIRECTORY_PERM)).ok()?;
+    }
+    Some(TrashDirectory {
+        root,
+        topdir: Some(topdir.to_path_buf()),
+    })
+}
+
+fn user_trash(topdir: &Path) -> Option<TrashDirectory> {
+    let root = topdir.join(format!(".Trash-{}", uid()));
+    if !root.exists() {
+        create_dir_all(&root).ok()?;
+        set_permissions(&root, Permissions::from_mode(DIRECTORY_PERM)).ok()?;
+    }
+    if std::fs::symlink_metadata(&root).ok()?.is_dir() {
+        Some(TrashDirectory {
+            root,
+            topdir: Some(topdir.to_path_buf()),
+        })
+    } else {
+        None
+    }
+}
+
+// Chooses the trash directory on the same device as the path so trashing is a rename
+pub fn trash_directory_for(path: &Path) -> Result<TrashDirectory, String> {
+    let home = home_trash()?;
+    let home_dev = home.root.ancestors().find_map(|ancestor| std::fs::metadata(ancestor).ok()).map(|metadata| metadata.dev());
+    let dev = std::fs::symlink_metadata(path).map_err(|e| e.to_string())?.dev();
+
+    if home_dev == Some(dev) {
+        return Ok(home);
+    }
+
+    let topdir = topdir(path).ok_or(format!("Mount point of {} not found", path.display()))?;
+    shared_trash(&topdir).or_else(|| user_trash(&topdir)).ok_or(format!("No usable trash directory on {}", topdir.display()))
+}
+
+// Lists the home trash and every trash directory found on mounted volumes
+pub fn trash_directories() -> Vec<TrashDirectory> {
+    let mut directories = Vec::new();
+    if let Ok(home) = home_trash() {
+        directories.push(home);
+    }
+
+    let uid = uid().to_string();
+    for mount_point in mount_points() {
+        for root in [mount_point.join(".Trash").join(&uid), mount_point.join(format!(".Trash-{}", uid))] {
+            if root.join("info").is_dir() && !directories.iter().any(|directory| directory.root == root) {
+                directories.push(TrashDirectory {
+                    root,
+                    topdir: Some(mount_point.clone()),
+                });
+            }
+        }
+    }
+    directories
+}
+
+pub fn mount_points() -> Vec<PathBuf> {
+    let mounts = std::fs::read_to_string("/proc/self/mounts").unwrap_or_default();
+    mounts.lines().filter_map(|line| line.split_whitespace().nth(1)).map(|mount_point| PathBuf::from(unescape_mount_path(mount_point))).collect()
+}
+
+// /proc/self/mounts escapes space, tab, newline and backslash as octal
+pub fn unescape_mount_path(path: &str) -> String {
+    let mut result = Vec::new();
+    let bytes = path.as_bytes();
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'\\' && i + 4 <= bytes.len() {
+            if let Some(value) = std::str::from_utf8(&bytes[i + 1..i + 4]).ok().and_then(|octal| u8::from_str_radix(octal, 8).ok()) {
+                result.push(value);
+                i += 4;
+                continue;
+            }
+        }
+        result.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&result).to_string()
+}
+
+// Works on raw bytes so that names which are not valid UTF-8 survive the round trip
+fn encode_path(path: &Path) -> String {
+    percent_encode(path.as_os_str().as_bytes(), PATH_ENCODE_SET).to_string()
+}
+
+fn decode_path(path: &str) -> PathBuf {
+    PathBuf::from(OsString::from_vec(percent_decode_str(path).collect()))
+}
+
+fn format_date() -> Result<String, String> {
+    let now = glib::DateTime::now_local().map_err(|e| e.to_string())?;
+    now.format(DATE_FORMAT).map(|date| date.to_string()).map_err(|e| e.to_string())
+}
+
+fn parse_date(date: &str) -> f64 {
+    glib::DateTime::from_iso8601(date, Some(&glib::TimeZone::local())).map(|date| date.to_unix() as f64 * 1000.0).unwrap_or_default()
+}
+
+fn parse_trash_info(content: &str) -> Option<(String, String)> {
+    let mut in_section = false;
+    let mut path = None;
+    let mut date = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_section = line == "[Trash Info]";
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+        if let Some((key, value)) = line.split_once('=') {
+            match key.trim() {
+                "Path" => path = Some(value.trim().to_string()),
+                "DeletionDate" => date = Some(value.trim().to_string()),
+                _ => {}
+            }
+        }
+    }
+    Some((path?, date.unwrap_or_default()))
+}
+
+pub fn read_entries(trash: &TrashDirectory) -> Vec<TrashEntry> {
+    let mut entries = Vec::new();
+    let info_directory = trash.root.join("info");
+    let Ok(infos) = std::fs::read_dir(&info_directory) else {
+        return entries;
+    };
+
+    for info in infos.flatten() {
+        let info_path = info.path();
+        if info_path.extension().map(|extension| extension != TRASH_INFO_EXTENSION).unwrap_or(true) {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(&info_path) else {
+            continue;
+        };
+        let Some((path, date)) = parse_trash_info(&content) else {
+            continue;
+        };
+        let decoded = decode_path(&path);
+        let original_path = match &trash.topdir {
+            Some(topdir) if decoded.is_relative() => topdir.join(decoded),
+            _ => decoded,
+        };
+        entries.push(TrashEntry {
+            trash: trash.clone(),
+            id: info_path.file_stem().unwrap_or_default().to_string_lossy().to_string(),
+            original_path,
+            deleted_date_ms: parse_date(&date),
+        });
+    }
+    entries
+}
+
+pub fn all_entries() -> Vec<TrashEntry> {
+    trash_directories().iter().flat_map(read_entries).collect()
+}
+
+pub(crate) fn directory_size(path: &Path) -> u64 {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => std::fs::read_dir(path).map(|entries| entries.flatten().map(|entry| directory_size(&entry.path())).sum()).unwrap_or_default(),
+        Ok(metadata) => metadata.len(),
+        Err(_) => 0,
+    }
+}
+
+fn info_mtime(info: &Path) -> u64 {
+    std::fs::metadata(info).ok().and_then(|metadata| metadata.modified().ok()).and_then(|time| time.duration_since(UNIX_EPOCH).ok()).map(|d| d.as_secs()).unwrap_or_default()
+}
+
+fn read_directory_sizes(trash: &TrashDirectory) -> HashMap<String, (u64, u64)> {
+    let content = std::fs::read_to_string(trash.root.join(DIRECTORY_SIZES)).unwrap_or_default();
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, ' ');
+            let size = parts.next()?.parse().ok()?;
+            let mtime = parts.next()?.parse().ok()?;
+            Some((percent_decode_str(parts.next()?).decode_utf8_lossy().to_string(), (size, mtime)))
+        })
+        .collect()
+}
+
+// Sizes of the entries, taking directories from the directorysizes cache while it is still current
+pub fn entry_sizes(trash: &TrashDirectory, entries: &[TrashEntry]) -> Vec<u64> {
+    let cache = read_directory_sizes(trash);
+    entries
+        .iter()
+        .map(|entry| match cache.get(&entry.id) {
+            Some((size, mtime)) if *mtime == info_mtime(&entry.info()) => *size,
+            _ => directory_size(&entry.file()),
+        })
+        .collect()
+}
+
+fn update_directory_sizes(trash: &TrashDirectory, remove: &[String], add: Option<(u64, u64, &str)>) -> Result<(), String> {
+    let _lock = DIRECTORY_SIZES_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let file = trash.root.join(DIRECTORY_SIZES);
+    let content = std::fs::read_to_string(&file).unwrap_or_default();
+    let removed: Vec<String> = remove.iter().map(|id| utf8_percent_encode(id, PATH_ENCODE_SET).to_string()).collect();
+    let mut lines: Vec<String> = content.lines().filter(|line| line.splitn(3, ' ').nth(2).map(|name| !removed.iter().any(|id| id == name)).unwrap_or(false)).map(|line| line.to_string()).collect();
+
+    if let Some((size, mtime, id)) = add {
+        lines.push(format!("{} {} {}", size, mtime, utf8_percent_encode(id, PATH_ENCODE_SET)));
+    }
+
+    // Write to a temporary file and rename so readers never see a partial file
+    let temp = trash.root.join(format!("{}.{}", DIRECTORY_SIZES, std::process::id()));
+    std::fs::write(&temp, lines.iter().map(|line| format!("{line}\n")).collect::<String>()).map_err(|e| e.to_string())?;
+    std::fs::rename(&temp, &file).map_err(|e| e.to_string())
+}
+
+// Creates the .trashinfo exclusively so two processes never pick the same name
+fn reserve_info(trash: &TrashDirectory, name: &str, content: &str) -> Result<String, String> {
+    let info_directory = trash.root.join("info");
+    for index in 1.. {
+        let id = if index == 1 {
+            name.to_string()
+        } else {
+            let path = Path::new(name);
+            match path.extension() {
+                Some(extension) => format!("{}.{}.{}", path.file_stem().unwrap_or_default().to_string_lossy(), index, extension.to_string_lossy()),
+                None => format!("{}.{}", name, index),
+            }
+        };
+        if trash.root.join("files").join(&id).exists() {
+            continue;
+        }
+        match OpenOptions::new().write(true).create_new(true).open(info_directory.join(format!("{}.{}", id, TRASH_INFO_EXTENSION))) {
+            Ok(mut file) => {
+                file.write_all(content.as_bytes()).map_err(|e| e.to_string())?;
+                return Ok(id);
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    unreachable!()
+}
+
+fn prepare(trash: &TrashDirectory) -> Result<(), String> {
+    for directory in ["files", "info"] {
+        let directory = trash.root.join(directory);
+        if !directory.exists() {
+            create_dir_all(&directory).map_err(|e| e.to_string())?;
+            set_permissions(&directory, Permissions::from_mode(DIRECTORY_PERM)).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
+
+// Renames into the trash, or copies then deletes when the trash is on another device
+fn move_in(path: &Path, target: &Path) -> Result<(), String> {
+    match std::fs::rename(path, target) {
+        Ok(_) => Ok(()),
+        Err(e) if e.raw_os_error() == Some(libc::EXDEV) => {
+            if let Err(e) = copy_recursive(path, target, &TransferControl::default(), &mut |_| {}) {
+                let _ = remove_path(target);
+                return Err(e);
+            }
+            remove_path(path)
+        }
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub fn trash_file(path: &Path) -> Result<(), String> {
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir().map_err(|e| e.to_string())?.join(path)
+    };
+    // Volumes without a usable trash directory, e.g. read-only top directories, fall back to the home trash
+    let trash = match trash_directory_for(&path).and_then(|trash| prepare(&trash).map(|_| trash)) {
+        Ok(trash) => trash,
+        Err(_) => {
+            let home = home_trash()?;
+            prepare(&home)?;
+            home
+        }
+    };
+
+    let stored_path = match &trash.topdir {
+        Some(topdir) => path.strip_prefix(topdir).map(|relative| relative.to_path_buf()).unwrap_or(path.clone()),
+        None => path.clone(),
+    };
+    let content = format!("[Trash Info]\nPath={}\nDeletionDate={}\n", encode_path(&stored_path), format_date()?);
+    let name = path.file_name().ok_or(format!("Cannot trash {}", path.display()))?.to_string_lossy().to_string();
+    let id = reserve_info(&trash, &name, &content)?;
+    let info = trash.root.join("info").join(format!("{}.{}", id, TRASH_INFO_EXTENSION));
+
+    let is_dir = std::fs::symlink_metadata(&path).map(|metadata| metadata.is_dir()).unwrap_or_default();
+    if let Err(e) = move_in(&path, &trash.root.join("files").join(&id)) {
+        let _ = std::fs::remove_file(&info);
+        return Err(e);
+    }
+
+    if is_dir {
+        let mtime = info_mtime(&info);
+        let size = directory_size(&trash.root.join("files").join(&id));
+        update_directory_sizes(&trash, &[], Some((size, mtime, &id)))?;
+    }
+
+    Ok(())
+}
+
+pub fn trash(paths: Vec<String>) -> Result<(), String> {
+    for path in paths {
+        trash_file(Path::new(&path))?;
+    }
+    Ok(())
+}
+
+fn remove_path(path: &Path) -> Result<(), String> {
+    match std::fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => std::fs::remove_dir_all(path).map_err(|e| e.to_string()),
+        Ok(_) => std::fs::remove_file(path).map_err(|e| e.to_string()),
+        Err(_) => Ok(()),
+    }
+}
+
+pub fn purge(entry: &TrashEntry) -> Result<(), String> {
+    remove_path(&entry.file())?;
+    let _ = std::fs::remove_file(entry.info());
+    update_directory_sizes(&entry.trash, &[entry.id.clone()], None)
+}
+
+// Finds a free name next to the occupied one, e.g. "file (2).txt"
+pub fn unique_path(path: &Path) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+    let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+    let parent = path.parent().unwrap_or(Path::new("/"));
+    (2..).map(|index| parent.join(format!("{} ({}){}", stem, index, extension))).find(|candidate| !candidate.exists()).unwrap()
+}
+
+fn forget(entry: &TrashEntry) -> Result<(), String> {
+    let _ = std::fs::remove_file(entry.info());
+    update_directory_sizes(&entry.trash, &[entry.id.clone()], None)
+}
+
+fn move_out(entry: &TrashEntry, target: &Path) -> Result<(), String> {
+    if let Some(parent) = target.parent() {
+        if !parent.exists() {
+            create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+    }
+    std::fs::rename(entry.file(), target).map_err(|e| e.to_string())?;
+    forget(entry)
+}
+
+// Restores to the original location, recreating missing parents and keeping both items when the location is occupied
+pub fn restore(entry: &TrashEntry) -> Result<PathBuf, String> {
+    let target = if entry.original_path.exists() {
+        unique_path(&entry.original_path)
+    } else {
+        entry.original_path.clone()
+    };
+    move_out(entry, &target)?;
+    Ok(target)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Conflict {
+    Replace,
+    Skip,
+    KeepBoth,
+    Cancel,
+}
+
+const COPY_BUFFER_SIZE: usize = 1024 * 1024;
+
+fn copy_recursive<F: FnMut(TransferStatus)>(from: &Path, to: &Path, control: &TransferControl, on_status: &mut F) -> Result<(), String> {
+    let metadata = std::fs::symlink_metadata(from).map_err(|e| e.to_string())?;
+    if metadata.file_type().is_symlink() {
+        let link = std::fs::read_link(from).map_err(|e| e.to_string())?;
+        return std::os::unix::fs::symlink(link, to).map_err(|e| e.to_string());
+    }
+
+    if metadata.is_dir() {
+        create_dir_all(to).map_err(|e| e.to_string())?;
+        for child in std::fs::read_dir(from).map_err(|e| e.to_string())?.flatten() {
+            copy_recursive(&child.path(), &to.join(child.file_name()), control, on_status)?;
+        }
+        return set_permissions(to, metadata.permissions()).map_err(|e| e.to_string());
+    }
+
+    let mut reader = std::fs::File::open(from).map_err(|e| e.to_string())?;
+    let mut writer = OpenOptions::new().write(true).create(true).truncate(true).open(to).map_err(|e| e.to_string())?;
+    let mut buffer = vec![0; COPY_BUFFER_SIZE];
+    loop {
+        if control.wait() {
+            return Err("Cancelled".to_string());
+        }
+        let size = reader.read(&mut buffer).map_err(|e| e.to_string())?;
+        if size == 0 {
+            break;
+        }
+        writer.write_all(&buffer[..size]).map_err(|e| e.to_string())?;
+        on_status(TransferStatus::Progress(size as u64));
+    }
+    set_permissions(to, metadata.permissions()).map_err(|e| e.to_string())
+}
+
+// Restores items into the destination folder, asking how to resolve each name conflict
+pub fn restore_to<F: FnMut(TransferStatus), C: FnMut(&Path) -> Conflict>(entries: &[TrashEntry], destination: &Path, control: &TransferControl, mut on_status: F, mut on_conflict: C) -> Result<(), String> {
+    let total_size = entries.iter().map(|entry| directory_size(&entry.file())).sum();
+    on_status(TransferStatus::Ready(entries.len() as u64, total_size));
+
+    for entry in entries {
+        if control.wait() {
+            return Ok(());
+        }
+
+        let name = entry.original_path.file_name().ok_or(format!("Invalid original path {:?}", entry.original_path))?;
+        let mut target = destination.join(name);
+        on_status(TransferStatus::Start(target.to_string_lossy().to_string()));
+
+        if target.exists() {
+            match on_conflict(&target) {
+                // The replaced item goes to the trash so it can still be recovered
+                Conflict::Replace => trash_file(&target)?,
+                Conflict::KeepBoth => target = unique_path(&target),
+                Conflict::Skip => {
+                    on_status(TransferStatus::End);
+                    continue;
+                }
+                Conflict::Cancel => return Ok(()),
+            }
+        }
+
+        if std::fs::rename(entry.file(), &target).is_ok() {
+            on_status(TransferStatus::Progress(directory_size(&target)));
+        } else {
+            // The trash lives on another volume, so copy out and drop the trashed copy
+            if let Err(e) = copy_recursive(&entry.file(), &target, control, &mut on_status) {
+                let _ = remove_path(&target);
+                return Err(e);
+            }
+            remove_path(&entry.file())?;
+        }
+        forget(entry)?;
+        on_status(TransferStatus::End);
+    }
+
+    Ok(())
+}
+
+// DeletionDate has only second precision, so items are matched by their .trashinfo file instead
+pub fn find(items: &[RecycleBinItem]) -> Vec<TrashEntry> {
+    all_entries().into_iter().filter(|entry| items.iter().any(|item| Path::new(&item.trash_id) == entry.info())).collect()
+}
+
+pub fn read_recycle_bin() -> Result<Vec<RecycleBinDirent>, String> {
+    Ok(all_entries()
+        .into_iter()
+        .map(|entry| {
+            let file = entry.file();
+            let attributes = crate::remote::LocalProvider.stat(&file.to_string_lossy()).unwrap_or_default();
+            let name = entry.original_path.file_name().unwrap_or_default().to_string_lossy().to_string();
+            RecycleBinDirent {
+                trash_id: entry.info().to_string_lossy().to_string(),
+                mime_type: if attributes.is_directory {
+                    String::new()
+                } else {
+                    zouni::fs::get_mime_type(file.to_string_lossy().to_string())
+                },
+                original_path: entry.original_path.to_string_lossy().to_string(),
+                deleted_date_ms: entry.deleted_date_ms,
+                attributes,
+                name,
+            }
+        })
+        .collect())
+}
+
+// Restores the most recently deleted item of each path
+pub fn undelete(paths: &[String]) -> Result<(), String> {
+    let entries = all_entries();
+    for path in paths {
+        if let Some(entry) = entries.iter().filter(|entry| Path::new(path) == entry.original_path).max_by(|a, b| a.deleted_date_ms.total_cmp(&b.deleted_date_ms)) {
+            restore(entry)?;
+        }
+    }
+    Ok(())
+}
+
+pub fn undelete_by_time(items: &[RecycleBinItem]) -> Result<(), String> {
+    for entry in find(items) {
+        restore(&entry)?;
+    }
+    Ok(())
+}
+
+pub fn delete_from_recycle_bin(items: &[RecycleBinItem]) -> Result<(), String> {
+    for entry in find(items) {
+        purge(&entry)?;
+    }
+    Ok(())
+}
+
+pub fn empty_recycle_bin() -> Result<(), String> {
+    for entry in all_entries() {
+        purge(&entry)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trash_info_is_parsed() {
+        let content = "[Other]\nPath=/ignored\n[Trash Info]\nPath=/home/user/a%20b.txt\nDeletionDate=2024-01-02T03:04:05\n";
+        assert_eq!(parse_trash_info(content), Some(("/home/user/a%20b.txt".to_string(), "2024-01-02T03:04:05".to_string())));
+        assert_eq!(parse_trash_info("[Trash Info]\nPath=/a\n"), Some(("/a".to_string(), String::new())));
+        assert_eq!(parse_trash_info("[Trash Info]\nDeletionDate=2024-01-02T03:04:05\n"), None);
+        assert_eq!(parse_trash_info("[Other]\nPath=/a\n"), None);
+    }
+
+    #[test]
+    fn paths_round_trip() {
+        let path = Path::new("/home/user/a b%é.txt");
+        let encoded = encode_path(path);
+        assert_eq!(encoded, "/home/user/a%20b%25%C3%A9.txt");
+        assert_eq!(decode_path(&encoded), path);
+
+        let path = Path::new(std::ffi::OsStr::from_bytes(b"/home/user/\xff.txt"));
+        let encoded = encode_path(path);
+        assert_eq!(encoded, "/home/user/%FF.txt");
+        assert_eq!(decode_path(&encoded), path);
+    }
+
+    #[test]
+    fn mount_paths_are_unescaped() {
+        assert_eq!(unescape_mount_path("/media/my\\040disk"), "/media/my disk");
+        assert_eq!(unescape_mount_path("/media/a\\\\b"), "/media/a\\\\b");
+        assert_eq!(unescape_mount_path("/media/a\\134b"), "/media/a\\b");
+        assert_eq!(unescape_mount_path("/media/a\\9"), "/media/a\\9");
+    }
+}
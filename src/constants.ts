@@ -32,6 +32,11 @@ export const DEFAULT_SETTINGS: Mp.Settings = {
     useOSIcon: false,
     rememberColumns: true,
     treeView: false,
+    connections: [],
+    trashPolicy: {},
+    thumbnailCache: {},
+    pollIntervals: {},
+    fileManagerService: false,
 };
 
 export const BROWSER_SHORTCUT_KEYS = ["f", "p", "r", "+", "-", "u", "g", "j"];
@@ -114,6 +119,9 @@ export const LINUX_SPECIAL_FOLDERS = {
 
 export const WSL_ROOT = "\\\\wsl.localhost";
 
+export const TREE_WATCH_DEPTH = 3;
+export const WATCH_EXCLUDES = [".git", "node_modules", "__pycache__"];
+
 export const INPUT_TEXT_BORDER_WIDTH = 2;
 export const COLUMN_HEADER_HEIGHT = 30;
 export const GRID_VERTICAL_MARGIN = 10;
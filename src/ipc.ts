@@ -112,11 +112,15 @@ type SymlinkRequest = {
 
 type InitArgs = {
     urls: string[];
+    options: { [key: string]: string };
     locales: string[];
     restore_position: boolean;
+    bounds: Mp.WindowBounds | null;
+    restorable: { crashed: boolean } | null;
 };
 
 export type RecycleBinItem = {
+    trash_id?: string;
     name: string;
     original_path: string;
     deleted_date_ms: number;
@@ -127,12 +131,36 @@ export type RecycleBinItem = {
 export type DeleteUndeleteRequest = {
     original_path: string;
     deleted_time_ms: number;
+    trash_id?: string;
 };
 
-export type ThumbnailArgs = {
+export type RestoreToRequest = {
+    items: DeleteUndeleteRequest[];
+    destination: string;
+};
+
+export type ThumbnailRequest = {
+    request_id: string;
     full_path: string;
+    video: boolean;
     width: number;
     height: number;
+    priority: number;
+};
+
+type TrustHostKeyRequest = {
+    uri: string;
+    fingerprint: string;
+};
+
+type PrefetchThumbnailsRequest = {
+    directory: string;
+    size: number;
+};
+
+type CachedThumbnail = {
+    full_path: string;
+    thumbnail_path: string;
 };
 
 type IconInfo = {
@@ -141,9 +169,21 @@ type IconInfo = {
     large: number[];
 };
 
-type NotifyRequest = {
+export type TrashUsage = {
+    mount_point: string;
+    trash_path: string;
+    item_count: number;
+    size: number;
+    volume_size: number;
+};
+
+export type NotifyRequest = {
     path: string;
-    network: boolean;
+    view: Mp.WatchView;
+    tab: number;
+    recursive: boolean;
+    depth?: number;
+    excludes: string[];
 };
 
 type TauriCommandMap = {
@@ -156,6 +196,15 @@ type TauriCommandMap = {
     open_path: TauriCommand<string, undefined>;
     open_path_with: TauriCommand<OpenWithArg, undefined>;
     open_in_new_window: TauriCommand<string, undefined>;
+    open_tab_context_menu: TauriCommand<Mp.Position, undefined>;
+    list_tabs: TauriCommand<undefined, Mp.WindowTabs>;
+    restore_session: TauriCommand<boolean, Mp.WindowBounds | null>;
+    open_tab: TauriCommand<Mp.Tab, Mp.Tab>;
+    update_tab: TauriCommand<Mp.Tab, undefined>;
+    activate_tab: TauriCommand<number, Mp.Tab | null>;
+    close_tab: TauriCommand<number, Mp.Tab | null>;
+    reopen_closed_tab: TauriCommand<undefined, Mp.Tab | null>;
+    detach_tab: TauriCommand<number, Mp.Tab | null>;
     show_app_selector: TauriCommand<string, undefined>;
     open_property_dielog: TauriCommand<string, undefined>;
     readdir: TauriCommand<ReadDirRequest, Dirent[]>;
@@ -168,6 +217,7 @@ type TauriCommandMap = {
     delete: TauriCommand<string[], undefined>;
     undelete: TauriCommand<string[], undefined>;
     undelete_by_time: TauriCommand<DeleteUndeleteRequest[], undefined>;
+    restore_to: TauriCommand<RestoreToRequest, undefined>;
     delete_from_recycle_bin: TauriCommand<DeleteUndeleteRequest[], undefined>;
     copy: TauriCommand<CopyInfo, undefined>;
     mv: TauriCommand<CopyInfo, undefined>;
@@ -183,6 +233,7 @@ type TauriCommandMap = {
     write_text_file: TauriCommand<WriteFileInfo, undefined>;
     watch: TauriCommand<NotifyRequest, undefined>;
     unwatch: TauriCommand<NotifyRequest, undefined>;
+    set_poll_intervals: TauriCommand<{ [mountPoint: string]: number }, undefined>;
     message: TauriCommand<DialogOptions, Mp.MessageResult>;
     open_terminal: TauriCommand<TerminalArgs, undefined>;
     launch_new: TauriCommand<undefined, undefined>;
@@ -197,12 +248,24 @@ type TauriCommandMap = {
     show_file_folder_dialog: TauriCommand<OpenFileFolderOption, string | null>;
     create_symlink: TauriCommand<SymlinkRequest, undefined>;
     read_recycle_bin: TauriCommand<undefined, RecycleBinItem[]>;
-    empty_recycle_bin: TauriCommand<undefined, undefined>;
-    to_thumbnail: TauriCommand<ThumbnailArgs, number[]>;
-    to_image_thumbnail: TauriCommand<string, number[]>;
+    empty_recycle_bin: TauriCommand<string | undefined, undefined>;
+    set_trash_policy: TauriCommand<Mp.TrashPolicy, undefined>;
+    set_thumbnail_cache_policy: TauriCommand<Mp.ThumbnailCachePolicy, undefined>;
+    clean_thumbnail_cache: TauriCommand<Mp.ThumbnailCachePolicy, Mp.ThumbnailCacheCleanResult>;
+    set_file_manager_service: TauriCommand<boolean, undefined>;
+    find_oversized_items: TauriCommand<string[], string[]>;
+    trash_usage: TauriCommand<undefined, TrashUsage[]>;
+    request_thumbnails: TauriCommand<ThumbnailRequest[], undefined>;
+    cancel_thumbnails: TauriCommand<string[], undefined>;
+    prefetch_thumbnails: TauriCommand<PrefetchThumbnailsRequest, CachedThumbnail[]>;
+    thumbnailer_mime_types: TauriCommand<undefined, string[]>;
     is_file: TauriCommand<string, boolean>;
     assoc_icons: TauriCommand<string[], { [key: string]: IconInfo }>;
     get_wsl_names: TauriCommand<undefined, string[]>;
+    set_connection_profiles: TauriCommand<Mp.ConnectionProfile[], undefined>;
+    disconnect: TauriCommand<string, undefined>;
+    unknown_host_key: TauriCommand<string, string | null>;
+    trust_host_key: TauriCommand<TrustHostKeyRequest, undefined>;
     undo: TauriCommand<undefined, undefined>;
     redo: TauriCommand<undefined, undefined>;
 };
@@ -219,7 +282,7 @@ export class IPC extends IPCBase {
     private label: string;
     private funcs: UnlistenFn[] = [];
 
-    constructor(label: RendererName) {
+    constructor(label: string) {
         super();
         this.label = label;
     }
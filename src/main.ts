@@ -1,22 +1,33 @@
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
 import util from "./util";
-import { HOME, OS, DEFAULT_LABLES } from "./constants";
-import { DeleteUndeleteRequest, Dirent, IPC, RecycleBinItem } from "./ipc";
+import { HOME, OS, DEFAULT_LABLES, DEFAULT_SORT_TYPE, TREE_WATCH_DEPTH, WATCH_EXCLUDES } from "./constants";
+import { DeleteUndeleteRequest, Dirent, IPC, NotifyRequest, RecycleBinItem } from "./ipc";
 import path from "./path";
 import { History } from "./history";
 import { t } from "./translation/useTranslation";
 import { dispatch, icons, listState, settings } from "./view/appStateReducer.svelte";
 
-const ipc = new IPC("View");
+const ipc = new IPC(getCurrentWebviewWindow().label);
 
 class Main {
     private initialized = false;
     private searchCache: { [key: string]: string[] } = { "": [] };
     private searchKeyword = "";
     private searchBackup: Mp.MediaFile[] = [];
-    private watchTargets = [HOME];
+    // Watch requests per tab, kept while the tab is in the background
+    private watches: { [tab: number]: NotifyRequest[] } = {};
+    private tab = 0;
     private history = new History();
     private pendingRenameFrom = "";
 
+    private get watchTargets(): NotifyRequest[] {
+        return (this.watches[this.tab] ??= []);
+    }
+
+    private set watchTargets(requests: NotifyRequest[]) {
+        this.watches[this.tab] = requests;
+    }
+
     onMainReady = async (dropTagetId: string): Promise<Mp.ReadyEvent> => {
         const drives = await util.getDriveInfo();
 
@@ -25,18 +36,43 @@ class Main {
         const locale = args.locales.some((locale) => locale.toLowerCase().includes("ja")) ? "ja" : "en";
         window.lang = locale;
 
+        const bounds = args.restorable ? await this.restoreSession(args.restorable.crashed) : args.bounds;
+
+        // A tab moved from another window or restored from the previous session is already registered
+        let tabs = await ipc.invoke("list_tabs", undefined);
+        if (!tabs.tabs.length) {
+            const tab = await ipc.invoke("open_tab", { id: 0, path: "", back: [], forward: [], sort: DEFAULT_SORT_TYPE, selection: [], scroll: 0 });
+            tabs = { tabs: [tab], active: tab.id };
+        }
+        this.tab = tabs.active;
+
         if (!this.initialized) {
             await ipc.invoke("prepare_menu", this.createColumnMenuItesm());
             await ipc.invoke("listen_devices", undefined);
             await ipc.invoke("listen_file_drop", dropTagetId);
+            await ipc.invoke("set_connection_profiles", settings.data.connections);
+            await ipc.invoke("set_poll_intervals", settings.data.pollIntervals);
+            if (navigator.userAgent.includes(OS.linux)) {
+                await ipc.invoke("set_trash_policy", settings.data.trashPolicy);
+                await ipc.invoke("set_thumbnail_cache_policy", settings.data.thumbnailCache);
+                await ipc.invoke("set_file_manager_service", settings.data.fileManagerService);
+            }
         }
 
         let selectId;
-        const item = args.urls.length ? await util.toFileFromPath(args.urls[0]) : null;
+        const tabPath = tabs.tabs.find((tab) => tab.id == tabs.active)?.path ?? "";
+        const url = args.urls.length ? args.urls[0] : tabPath;
+        const restorable = url && !util.isHome(url) && (util.isRecycleBin(url) || (await util.exists(url)));
+        const item = restorable && !util.isRecycleBin(url) ? await util.toFileFromPath(url) : null;
         let files: Mp.MediaFile[] = [];
         let directory = HOME;
-        if (item) {
-            if (item.isFile) {
+        if (restorable && util.isRecycleBin(url)) {
+            directory = url;
+            const result = await this.readFiles(url);
+            files = result.files;
+        } else if (item) {
+            // Folders given with --select are shown selected in their parent
+            if (item.isFile || args.options.reveal == "true") {
                 selectId = item.id;
                 directory = item.dir;
                 const result = await this.readFiles(item.dir);
@@ -61,9 +97,22 @@ class Main {
             },
             selectId,
             restorePosition: args.restore_position,
+            bounds: bounds ?? undefined,
+            options: args.options,
+            tabs,
         };
     };
 
+    // Windows are restored silently after a normal exit and on confirmation after a crash
+    private restoreSession = async (crashed: boolean) => {
+        let accept = true;
+        if (crashed) {
+            const result = await ipc.invoke("message", { dialog_type: "confirm", kind: "warning", message: t("restoreSession"), ok_label: t("yes"), cancel_label: t("no") });
+            accept = result.button == t("yes") && !result.cancelled;
+        }
+        return await ipc.invoke("restore_session", accept);
+    };
+
     private createColumnMenuItesm = () => {
         return DEFAULT_LABLES.filter((column) => column.sortKey != "name" && column.sortKey != "directory").map((column) => {
             return {
@@ -98,6 +147,10 @@ class Main {
             return { files: [], directory, navigation, failed: false, drives };
         }
 
+        if (!(await this.confirmHostKey(directory))) {
+            return null;
+        }
+
         if (!util.isRecycleBin(directory)) {
             const found = await util.exists(directory);
             if (!found) {
@@ -121,46 +174,85 @@ class Main {
         };
     };
 
+    // SFTP hosts missing from known_hosts are trusted only after the user confirms the fingerprint
+    private confirmHostKey = async (directory: string) => {
+        if (!directory.startsWith("sftp://")) return true;
+
+        try {
+            const fingerprint = await ipc.invoke("unknown_host_key", directory);
+            if (!fingerprint) return true;
+
+            const result = await ipc.invoke("message", { dialog_type: "confirm", kind: "warning", message: `${t("unknownHostKey")}\n${fingerprint}`, ok_label: t("yes"), cancel_label: t("no") });
+            if (result.button != t("yes") || result.cancelled) return false;
+
+            await ipc.invoke("trust_host_key", { uri: directory, fingerprint });
+            return true;
+        } catch (ex: any) {
+            await util.showErrorMessage(ex);
+            return false;
+        }
+    };
+
     private isWatchable = (target: string) => {
         if (util.isHome(target)) return false;
         if (util.isRecycleBin(target)) return false;
+        if (target.includes("://")) return false;
 
         return true;
     };
 
-    private isNetwork = (target: string) => {
-        return util.isWsl(target);
+    private toWatchRequest = (target: string, view: Mp.WatchView, recursive: boolean): NotifyRequest => {
+        return {
+            path: target,
+            view,
+            tab: this.tab,
+            recursive,
+            depth: view == "list" ? TREE_WATCH_DEPTH : undefined,
+            excludes: WATCH_EXCLUDES,
+        };
+    };
+
+    private findWatch = (target: string, view: Mp.WatchView) => {
+        return this.watchTargets.findIndex((request) => request.path == target && request.view == view);
     };
 
     startWatch = async (target: string) => {
         await this.abortWatch();
 
         if (this.isWatchable(target)) {
-            this.watchTargets.push(target);
-            ipc.invoke("watch", { path: target, network: this.isNetwork(target) });
+            // Tree view shows subfolders, so watch a few levels below the current folder
+            const request = this.toWatchRequest(target, "list", settings.data.treeView);
+            this.watchTargets.push(request);
+            ipc.invoke("watch", request);
         }
     };
 
     private abortWatch = async () => {
         if (!this.watchTargets.length) return;
-        await Promise.all(this.watchTargets.map(async (target) => await ipc.invoke("unwatch", { path: target, network: this.isNetwork(target) })));
+        await Promise.all(this.watchTargets.map(async (request) => await ipc.invoke("unwatch", request)));
         this.watchTargets = [];
     };
 
-    private addWatch = async (target: string) => {
+    private addWatch = async (target: string, view: Mp.WatchView = "list") => {
         if (!this.isWatchable(target)) return;
-        if (this.watchTargets.includes(target)) return;
+        if (this.findWatch(target, view) >= 0) return;
 
-        this.watchTargets.push(target);
-        ipc.invoke("watch", { path: target, network: this.isNetwork(target) });
+        const request = this.toWatchRequest(target, view, view == "search");
+        this.watchTargets.push(request);
+        ipc.invoke("watch", request);
     };
 
     unwatch = async (target: string) => {
         if (!this.isWatchable(target)) return;
-        if (!this.watchTargets.includes(target)) return;
+        const index = this.findWatch(target, "list");
+        if (index < 0) return;
 
-        await ipc.invoke("unwatch", { path: target, network: this.isNetwork(target) });
-        this.watchTargets.splice(this.watchTargets.indexOf(target), 1);
+        await ipc.invoke("unwatch", this.watchTargets[index]);
+        this.watchTargets.splice(index, 1);
+    };
+
+    private hasSearchWatch = () => {
+        return this.watchTargets.some((request) => request.view == "search");
     };
 
     readDirectory = async (directory: string): Promise<Mp.ReadResult> => {
@@ -304,6 +396,8 @@ class Main {
         } else {
             const allDirents = await ipc.invoke("readdir", { directory: e.dir, recursive: true });
             this.searchCache[e.dir] = allDirents.filter((direcnt) => !direcnt.attributes.is_system).map((dirent) => path.join(dirent.parent_path, dirent.name));
+            // Keep the cache in sync with changes anywhere below the searched folder
+            this.addWatch(e.dir, "search");
             searchResult = await this.filterCache(e.dir, key);
         }
 
@@ -339,6 +433,28 @@ class Main {
         await ipc.invoke("open_path_with", { full_path: safeFullPath, app_path: appPath });
     };
 
+    setFileManagerService = async () => {
+        if (navigator.userAgent.includes(OS.linux)) {
+            await ipc.invoke("set_file_manager_service", settings.data.fileManagerService);
+        }
+    };
+
+    setTrashPolicy = async () => {
+        if (navigator.userAgent.includes(OS.linux)) {
+            await ipc.invoke("set_trash_policy", settings.data.trashPolicy);
+        }
+    };
+
+    setThumbnailCachePolicy = async () => {
+        if (navigator.userAgent.includes(OS.linux)) {
+            await ipc.invoke("set_thumbnail_cache_policy", settings.data.thumbnailCache);
+        }
+    };
+
+    cleanThumbnailCache = async (policy: Mp.ThumbnailCachePolicy) => {
+        return await ipc.invoke("clean_thumbnail_cache", policy);
+    };
+
     openInNewWindow = async (fullPath: string) => {
         await ipc.invoke("open_in_new_window", fullPath);
     };
@@ -416,6 +532,47 @@ class Main {
         await ipc.invoke("open_terminal", { path: dir, admin });
     };
 
+    changeTab = (tab: number) => {
+        this.tab = tab;
+        this.searchBackup = [];
+    };
+
+    listTabs = async () => {
+        return await ipc.invoke("list_tabs", undefined);
+    };
+
+    openTab = async (tab: Mp.Tab) => {
+        return await ipc.invoke("open_tab", tab);
+    };
+
+    updateTab = async (tab: Mp.Tab) => {
+        await ipc.invoke("update_tab", tab);
+    };
+
+    activateTab = async (tab: number) => {
+        return await ipc.invoke("activate_tab", tab);
+    };
+
+    closeTab = async (tab: number) => {
+        await Promise.all((this.watches[tab] ?? []).map(async (request) => await ipc.invoke("unwatch", request)));
+        delete this.watches[tab];
+        return await ipc.invoke("close_tab", tab);
+    };
+
+    reopenClosedTab = async () => {
+        return await ipc.invoke("reopen_closed_tab", undefined);
+    };
+
+    detachTab = async (tab: number) => {
+        await Promise.all((this.watches[tab] ?? []).map(async (request) => await ipc.invoke("unwatch", request)));
+        delete this.watches[tab];
+        return await ipc.invoke("detach_tab", tab);
+    };
+
+    openTabContextMenu = async (e: Mp.Position) => {
+        await ipc.invoke("open_tab_context_menu", e);
+    };
+
     launchNew = async () => {
         await ipc.invoke("launch_new", undefined);
     };
@@ -484,7 +641,19 @@ class Main {
 
     trashItems = async (e: Mp.TrashItemRequest) => {
         try {
-            const fullPaths = e.files.map((file) => file.fullPath);
+            let fullPaths = e.files.map((file) => file.fullPath);
+
+            if (navigator.userAgent.includes(OS.linux)) {
+                const oversized = await ipc.invoke("find_oversized_items", fullPaths);
+                if (oversized.length) {
+                    const result = await ipc.invoke("message", { dialog_type: "confirm", kind: "warning", message: t("tooLargeToTrash"), ok_label: t("yes"), cancel_label: t("no") });
+                    if (result.button != t("yes") || result.cancelled) return;
+                    await ipc.invoke("delete", oversized);
+                    fullPaths = fullPaths.filter((fullPath) => !oversized.includes(fullPath));
+                    if (!fullPaths.length) return;
+                }
+            }
+
             await ipc.invoke("trash", fullPaths);
 
             this.trackOperation("Trash", [], "", fullPaths);
@@ -524,6 +693,7 @@ class Main {
                     return {
                         original_path: this.toFilePath(request.fullPath),
                         deleted_time_ms: request.deletedDate,
+                        trash_id: request.trashId,
                     };
                 });
                 await ipc.invoke("undelete_by_time", request);
@@ -536,6 +706,28 @@ class Main {
         }
     };
 
+    restoreItemsTo = async (e: Mp.UndeleteItemRequest, defaultPath: string) => {
+        if (!e.items) {
+            return util.showErrorMessage("Invalid undelete arguments");
+        }
+
+        const destination = await this.showFileFolderDialog(t("restoreTo"), defaultPath, true);
+        if (!destination) return;
+
+        try {
+            const items: DeleteUndeleteRequest[] = e.items.map((request) => {
+                return {
+                    original_path: this.toFilePath(request.fullPath),
+                    deleted_time_ms: request.deletedDate,
+                    trash_id: request.trashId,
+                };
+            });
+            await ipc.invoke("restore_to", { items, destination });
+        } catch (ex: any) {
+            util.showErrorMessage(ex);
+        }
+    };
+
     deleteFromRecycleBin = async (e: Mp.UndeleteItemRequest) => {
         if (e.undeleteSpecific && !e.items) {
             return util.showErrorMessage("Invalid undelete arguments");
@@ -560,12 +752,14 @@ class Main {
             return {
                 original_path: this.toFilePath(request.fullPath),
                 deleted_time_ms: request.deletedDate,
+                trash_id: request.trashId,
             };
         });
         await ipc.invoke("delete_from_recycle_bin", request);
     };
 
-    emptyRecycleBin = async (): Promise<boolean> => {
+    // Empties the recycle bin of one volume when the mount point is given, otherwise all of them
+    emptyRecycleBin = async (mountPoint?: string): Promise<boolean> => {
         if (navigator.userAgent.includes(OS.linux)) {
             const result = await ipc.invoke("message", {
                 title: t("recycleBin"),
@@ -580,10 +774,14 @@ class Main {
                 return false;
             }
         }
-        await ipc.invoke("empty_recycle_bin", undefined);
+        await ipc.invoke("empty_recycle_bin", mountPoint);
         return true;
     };
 
+    getTrashUsage = async () => {
+        return await ipc.invoke("trash_usage", undefined);
+    };
+
     getPathsFromClipboard = async (targets: Mp.MediaFile[], operation: Mp.ClipboardOperation): Promise<Mp.PasteData> => {
         const failedResult = { fullPaths: [], copy: true };
 
@@ -644,7 +842,24 @@ class Main {
         }
     };
 
+    private isVisibleDir = (directory: string) => {
+        return directory == listState.currentDir.fullPath || directory in listState.expandedDir;
+    };
+
     onWatchEvent = async (e: Mp.WatchEvent, files: Mp.MediaFile[]) => {
+        if (e.operation == "Limited") {
+            await util.showErrorMessage(t("watchLimitReached"));
+            return;
+        }
+
+        if (e.operation == "Unavailable") {
+            await util.showErrorMessage(t("liveUpdatesUnavailable"));
+            return;
+        }
+
+        // The recursive search watch covers the current folder too, so it takes over while it exists
+        if (e.view != (this.hasSearchWatch() ? "search" : "list")) return;
+
         const hasSearchCache = listState.currentDir.fullPath in this.searchCache;
 
         switch (e.operation) {
@@ -669,15 +884,16 @@ class Main {
                         this.searchBackup.push(...newItems);
                     }
                 } else {
-                    const newItems = await Promise.all(e.to_paths.map(async (fullPath) => await util.toFileFromPath(fullPath)));
+                    const visiblePaths = e.to_paths.filter((fullPath) => this.isVisibleDir(path.dirname(fullPath)));
+                    const newItems = await Promise.all(visiblePaths.map(async (fullPath) => await util.toFileFromPath(fullPath)));
                     files.push(...newItems);
                 }
 
                 break;
             }
             case "Remove": {
-                const removing = e.to_paths.map((removingFullPath) => files.findIndex((file) => file.fullPath == removingFullPath));
-                removing.forEach((i) => files.splice(i, 1));
+                const removing = e.to_paths.map((removingFullPath) => files.findIndex((file) => file.fullPath == removingFullPath)).filter((i) => i >= 0);
+                removing.sort((a, b) => b - a).forEach((i) => files.splice(i, 1));
 
                 if (this.searchBackup.length) {
                     this.searchBackup = this.searchBackup.filter((file) => !e.to_paths.includes(file.fullPath));
@@ -695,6 +911,21 @@ class Main {
 
                 break;
             }
+            case "Modify":
+            case "Metadata": {
+                e.to_paths.forEach((fullPath, i) => {
+                    const fileIndex = files.findIndex((file) => file.fullPath == fullPath);
+                    if (fileIndex >= 0) {
+                        files[fileIndex] = util.updateFileStat(files[fileIndex], e.attributes[i]);
+                    }
+
+                    const backupIndex = this.searchBackup.findIndex((file) => file.fullPath == fullPath);
+                    if (backupIndex >= 0) {
+                        this.searchBackup[backupIndex] = util.updateFileStat(this.searchBackup[backupIndex], e.attributes[i]);
+                    }
+                });
+                break;
+            }
             case "Rename": {
                 // Sometimes "from" and "to" come separately. Do not process until "to" comes
                 if (!e.to_paths.length) {
@@ -705,7 +936,9 @@ class Main {
                 const oldFullPath = this.pendingRenameFrom ? this.pendingRenameFrom : e.from_paths[0];
                 const fileIndex = files.findIndex((file) => file.fullPath == oldFullPath);
                 const newMediaFile = await util.toFileFromPath(newFullPath);
-                files[fileIndex] = newMediaFile;
+                if (fileIndex >= 0) {
+                    files[fileIndex] = newMediaFile;
+                }
 
                 if (this.searchBackup.length) {
                     const fileIndex = this.searchBackup.findIndex((file) => file.fullPath == oldFullPath);
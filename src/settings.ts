@@ -1,11 +1,12 @@
 import { appDataDir } from "@tauri-apps/api/path";
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
 import { DEFAULT_SETTINGS } from "./constants";
 import { IPC } from "./ipc";
 import path from "./path";
 
-const ipc = new IPC("View");
+const ipc = new IPC(getCurrentWebviewWindow().label);
 const SETTING_FILE_NAME = "explite.settings.json";
-const EXCEPTION_KEYS = ["columnHistory"];
+const EXCEPTION_KEYS = ["columnHistory", "trashPolicy", "thumbnailCache", "pollIntervals"];
 
 export default class Settings {
     private file = "";
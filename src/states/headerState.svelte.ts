@@ -29,6 +29,10 @@ export class HeaderUpdater {
         state.search = { ...state.search, searching: false, key: "" };
     };
 
+    static setSearchKey = (key: string) => {
+        state.search = { ...state.search, key };
+    };
+
     static startSearch = () => {
         state.search = { ...state.search, searching: true, key: state.search.key.trim() };
     };
@@ -189,6 +189,11 @@ export class ListUpdater {
         column.width = slideState.initial + width;
     };
 
+    static restoreSortType = (sortType: Mp.SortType) => {
+        state.sortType = sortType;
+        this.sort(state.files);
+    };
+
     static updateSortType = (key: Mp.SortKey) => {
         const asc = state.sortType.key == key ? !state.sortType.asc : true;
         state.sortType = {
@@ -9,7 +9,7 @@ const state: SettingsState = $state({ data: DEFAULT_SETTINGS });
 
 export { state as settings };
 
-export type PreferenceAction = { theme: Mp.Theme; appMenuItems: Mp.AppMenuItem[]; allowMoveColumn: boolean; useOSIcon: boolean; rememberColumns: boolean; treeView: boolean };
+export type PreferenceAction = { theme: Mp.Theme; appMenuItems: Mp.AppMenuItem[]; allowMoveColumn: boolean; useOSIcon: boolean; rememberColumns: boolean; treeView: boolean; fileManagerService: boolean; trashPolicy: Mp.TrashPolicy; thumbnailCache: Mp.ThumbnailCachePolicy };
 
 export class SettingsUpdater {
     static updatePreference = (action: PreferenceAction) => {
@@ -18,6 +18,9 @@ export class SettingsUpdater {
         state.data.appMenuItems = action.appMenuItems;
         state.data.useOSIcon = action.useOSIcon;
         state.data.rememberColumns = action.rememberColumns;
+        state.data.fileManagerService = action.fileManagerService;
+        state.data.trashPolicy = action.trashPolicy;
+        state.data.thumbnailCache = action.thumbnailCache;
         if (state.data.treeView != action.treeView) {
             if (!action.treeView) {
                 ListUpdater.clearTreeState();
@@ -0,0 +1,27 @@
+type TabState = {
+    tabs: Mp.Tab[];
+    active: number;
+    hoverTabId: number;
+};
+
+const state: TabState = $state({
+    tabs: [],
+    active: 0,
+    hoverTabId: 0,
+});
+
+export { state as tabState };
+
+export class TabUpdater {
+    static setTabs = (windowTabs: Mp.WindowTabs) => {
+        state.tabs = windowTabs.tabs;
+        state.active = windowTabs.active;
+    };
+
+    static updateTab = (tab: Mp.Tab) => {
+        const index = state.tabs.findIndex((current) => current.id == tab.id);
+        if (index >= 0) {
+            state.tabs[index] = tab;
+        }
+    };
+}
@@ -0,0 +1,137 @@
+import { getCurrentWebviewWindow } from "@tauri-apps/api/webviewWindow";
+import { convertFileSrc } from "@tauri-apps/api/core";
+import { IPC, ThumbnailRequest } from "./ipc";
+import { LARGE_ICON_SIZE } from "./constants";
+
+type PendingThumbnail = {
+    resolve: (data: string) => void;
+    reject: (reason: string) => void;
+};
+
+const ipc = new IPC(getCurrentWebviewWindow().label);
+
+// Device pixels of a grid icon. devicePixelRatio also follows the webview zoom
+const thumbnailSize = () => Math.ceil(LARGE_ICON_SIZE * window.devicePixelRatio);
+
+// Thumbnails are made by a worker pool in the backend and delivered as events
+class ThumbnailLoader {
+    private pending: { [requestId: string]: PendingThumbnail } = {};
+    private requests: ThumbnailRequest[] = [];
+    private cancels: string[] = [];
+    private flushing = false;
+    private listening = false;
+    // Thumbnails of the prefetched directory by full path
+    private cache: { [fullPath: string]: string } = {};
+    private prefetchDirectory = "";
+    private prefetchSize = 0;
+    private thumbnailerMimeTypes = new Set<string>();
+
+    constructor() {
+        ipc.invoke("thumbnailer_mime_types", undefined).then((mimeTypes) => (this.thumbnailerMimeTypes = new Set(mimeTypes)));
+    }
+
+    load = (fullPath: string, isVideo: boolean, priority = 0) => {
+        this.listen();
+
+        const requestId = crypto.randomUUID();
+        const promise = new Promise<string>((resolve, reject) => {
+            this.pending[requestId] = { resolve, reject };
+        });
+        const size = thumbnailSize();
+        this.requests.push({ request_id: requestId, full_path: fullPath, video: isVideo, width: size, height: size, priority });
+        this.scheduleFlush();
+
+        return { requestId, promise };
+    };
+
+    cancel = (requestIds: string[]) => {
+        const unsent = requestIds.filter((requestId) => this.requests.some((request) => request.request_id == requestId));
+        this.requests = this.requests.filter((request) => !unsent.includes(request.request_id));
+        this.cancels.push(...requestIds.filter((requestId) => requestId in this.pending && !unsent.includes(requestId)));
+        requestIds.forEach((requestId) => delete this.pending[requestId]);
+        this.scheduleFlush();
+    };
+
+    // Cached thumbnails are available at once, the rest arrive as prefetch events
+    prefetch = async (directory: string) => {
+        this.listen();
+
+        const size = thumbnailSize();
+        if (directory == this.prefetchDirectory && size == this.prefetchSize) return;
+        this.prefetchDirectory = directory;
+        this.prefetchSize = size;
+        this.cache = {};
+
+        try {
+            const hits = await ipc.invoke("prefetch_thumbnails", { directory, size });
+            if (directory != this.prefetchDirectory) return;
+            hits.forEach((hit) => (this.cache[hit.full_path] = convertFileSrc(hit.thumbnail_path)));
+        } catch {
+            // Items request their own thumbnails when the directory cannot be listed
+        }
+    };
+
+    // Files other than pictures and videos have thumbnails when the system has a thumbnailer for them
+    hasThumbnailer = (mimeType: string) => {
+        return this.thumbnailerMimeTypes.has(mimeType);
+    };
+
+    cached = (fullPath: string): string | undefined => {
+        return this.cache[fullPath];
+    };
+
+    private listen = () => {
+        if (this.listening) return;
+        this.listening = true;
+        ipc.receive("thumbnail_event", this.onThumbnailEvent);
+        ipc.receive("thumbnail_prefetch_event", this.onPrefetchEvent);
+    };
+
+    // Items mounted in the same frame are sent in one request
+    private scheduleFlush = () => {
+        if (this.flushing) return;
+        this.flushing = true;
+        queueMicrotask(this.flush);
+    };
+
+    private flush = async () => {
+        this.flushing = false;
+        const cancels = this.cancels;
+        const requests = this.requests;
+        this.cancels = [];
+        this.requests = [];
+
+        if (cancels.length) {
+            await ipc.invoke("cancel_thumbnails", cancels);
+        }
+        if (requests.length) {
+            await ipc.invoke("request_thumbnails", requests);
+        }
+    };
+
+    private onThumbnailEvent = (e: Mp.ThumbnailEvent) => {
+        const pending = this.pending[e.request_id];
+        if (!pending) return;
+
+        delete this.pending[e.request_id];
+        if (e.data) {
+            pending.resolve(toDataUrl(e.data));
+        } else {
+            pending.reject(e.error ?? "");
+        }
+    };
+
+    private onPrefetchEvent = (e: Mp.ThumbnailPrefetchEvent) => {
+        if (e.directory != this.prefetchDirectory) return;
+
+        if (e.thumbnail_path) {
+            this.cache[e.full_path] = convertFileSrc(e.thumbnail_path);
+        } else if (e.data) {
+            this.cache[e.full_path] = toDataUrl(e.data);
+        }
+    };
+}
+
+const toDataUrl = (data: number[]) => `data:image/jpeg;base64,${Uint8Array.from(data).toBase64()}`;
+
+export const thumbnails = new ThumbnailLoader();
@@ -22,4 +22,12 @@ export const en: Mp.Labels = {
     recycleBin: "Recycle Bin",
     deleteFromRecycleBinMsg: "Are you sure to delete files completely?",
     emptyRecycleBinMsg: "Are you sure to delete all files completely?",
+    restoreTo: "Select a folder to restore to",
+    watchLimitReached: "Too many folders to watch. Only changes in the current folder will be shown.",
+    liveUpdatesUnavailable: "Live updates are not available for this folder. Reload to see changes.",
+    tooLargeToTrash: "Some items are too large for the recycle bin. Delete them permanently?",
+    restoreSession: "The app did not close properly last time. Restore the previous windows and tabs?",
+    unknownHostKey: "The authenticity of this host cannot be established. Trust this key fingerprint and connect?",
+    trashItems: "items",
+    emptyVolumeTrash: "Empty",
 };
@@ -13,6 +13,7 @@ export const ja: Mp.Labels = {
     newFile: "新しいファイル",
     newFolder: "新しいフォルダー",
     deleteConfirm: "このファイルを完全に削除しますか？",
+    restoreSession: "前回は正常に終了しませんでした。前回のウィンドウとタブを復元しますか？",
     yes: "はい",
     no: "いいえ",
     extensionFile: "ファイル",
@@ -22,4 +23,11 @@ export const ja: Mp.Labels = {
     recycleBin: "ゴミ箱",
     deleteFromRecycleBinMsg: "ファイルを完全に削除しますか？",
     emptyRecycleBinMsg: "これらのファイルを完全に削除しますか？",
+    restoreTo: "戻す場所を選択",
+    watchLimitReached: "監視できるフォルダーの上限に達しました。現在のフォルダーの変更のみ表示されます。",
+    liveUpdatesUnavailable: "このフォルダーの変更は自動で反映されません。変更を確認するには再読み込みしてください。",
+    tooLargeToTrash: "サイズが大きすぎるためゴミ箱に移動できない項目があります。完全に削除しますか？",
+    unknownHostKey: "このホストの真正性を確認できません。このキーのフィンガープリントを信頼して接続しますか？",
+    trashItems: "項目",
+    emptyVolumeTrash: "空にする",
 };
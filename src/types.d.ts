@@ -42,9 +42,12 @@ declare global {
         "start-rename": Mp.AnyEvent;
         "after-rename": Mp.RenameResult;
         moved: Mp.MoveItemResult;
-        contextmenu_event: keyof MainContextMenuSubTypeMap | FavContextMenuSubTypeMap;
-        watch_event: Mp.WatchEvent;
+        contextmenu_event: Mp.ContextMenuEvent;
+        watch_event: Mp.WatchEvent[];
+        open_request: Mp.OpenRequest;
         device_event: Mp.DeviceEvent;
+        thumbnail_event: Mp.ThumbnailEvent;
+        thumbnail_prefetch_event: Mp.ThumbnailPrefetchEvent;
     };
 
     namespace Mp {
@@ -67,9 +70,46 @@ declare global {
             AdminTerminal: null;
             Delete: null;
             Undelete: null;
+            RestoreTo: null;
             EmptyRecycleBin: null;
             DeleteFromRecycleBin: null;
             AutoAdjustColumnWidth: null;
+            OpenInNewTab: null;
+        };
+
+        type TabContextMenuSubTypeMap = {
+            DuplicateTab: null;
+            MoveTabToNewWindow: null;
+            CloseTab: null;
+            ReopenClosedTab: null;
+        };
+
+        type ContextMenuEvent = {
+            id: keyof MainContextMenuSubTypeMap | keyof FavContextMenuSubTypeMap | keyof TabContextMenuSubTypeMap | SortKey;
+            tab: number;
+        };
+
+        type Tab = {
+            id: number;
+            path: string;
+            back: string[];
+            forward: string[];
+            sort: SortType;
+            selection: string[];
+            scroll: number;
+        };
+
+        type WindowBounds = {
+            x: number;
+            y: number;
+            width: number;
+            height: number;
+            maximized: boolean;
+        };
+
+        type WindowTabs = {
+            tabs: Tab[];
+            active: number;
         };
 
         type FavContextMenuSubTypeMap = {
@@ -109,6 +149,51 @@ declare global {
             useOSIcon: boolean;
             rememberColumns: boolean;
             treeView: boolean;
+            connections: ConnectionProfile[];
+            trashPolicy: TrashPolicy;
+            // Linux only, limits of the freedesktop thumbnail cache
+            thumbnailCache: ThumbnailCachePolicy;
+            // Poll interval in seconds per network mount point, 0 disables live updates
+            pollIntervals: { [mountPoint: string]: number };
+            // Linux only, claims org.freedesktop.FileManager1
+            fileManagerService: boolean;
+        };
+
+        type TrashPolicy = {
+            max_age_days?: number;
+            max_size_gb?: number;
+            max_percent?: number;
+            prompt_size_mb?: number;
+        };
+
+        type ThumbnailCachePolicy = {
+            max_age_days?: number;
+            max_size_mb?: number;
+        };
+
+        type ThumbnailCacheCleanResult = {
+            removed_count: number;
+            removed_size: number;
+            remaining_count: number;
+            remaining_size: number;
+            failed_count: number;
+        };
+
+        type ConnectionAuth =
+            | { type: "Agent" }
+            | { type: "Key"; private_key: string; passphrase?: string }
+            | { type: "Basic"; password: string }
+            | { type: "Token"; token: string };
+
+        type ConnectionProfile = {
+            id: string;
+            name: string;
+            scheme: "sftp" | "dav" | "davs";
+            host: string;
+            port?: number;
+            user: string;
+            auth: ConnectionAuth;
+            root?: string;
         };
 
         type Preference = {
@@ -162,6 +247,7 @@ declare global {
             ddate: number;
             ddateString: string;
             originalPath: string;
+            trashId?: string;
             mimeType: string;
             treeState?: TreeState;
         };
@@ -177,6 +263,9 @@ declare global {
             locale: Mp.LocaleName;
             selectId?: string;
             restorePosition: boolean;
+            bounds?: WindowBounds;
+            options: { [key: string]: string };
+            tabs: WindowTabs;
         };
 
         type LoadEvent = {
@@ -215,7 +304,7 @@ declare global {
             disks: Mp.DriveInfo[];
         };
 
-        type Navigation = "Direct" | "Back" | "Forward" | "Reload" | "PathSelect";
+        type Navigation = "Direct" | "Back" | "Forward" | "Reload" | "PathSelect" | "Tab";
         type SelectEvent = {
             fullPath: string;
             isFile: boolean;
@@ -256,6 +345,7 @@ declare global {
         type UndeleteItem = {
             fullPath: string;
             deletedDate: number;
+            trashId?: string;
         };
 
         type MarkItemRequest = {
@@ -339,10 +429,41 @@ declare global {
             event: "Added" | "Removed";
         };
 
+        type WatchView = "list" | "search";
+
+        type WatchAttribute = {
+            mtime_ms: number;
+            birthtime_ms: number;
+            size: number;
+        };
+
         type WatchEvent = {
-            operation: "Create" | "Remove" | "Rename";
+            operation: "Create" | "Remove" | "Rename" | "Modify" | "Metadata" | "Limited" | "Unavailable" | "Rescan" | "RootMoved" | "RootRemoved";
             to_paths: string[];
             from_paths: string[];
+            view: WatchView;
+            tab: number;
+            attributes: WatchAttribute[];
+        };
+
+        type ThumbnailEvent = {
+            request_id: string;
+            data: number[] | null;
+            error: string | null;
+        };
+
+        type ThumbnailPrefetchEvent = {
+            directory: string;
+            full_path: string;
+            thumbnail_path: string | null;
+            data: number[] | null;
+            done: number;
+            total: number;
+        };
+
+        type OpenRequest = {
+            paths: string[];
+            options: { [key: string]: string };
         };
 
         type Operation = "Copy" | "Move" | "Trash" | "Create" | "Undelete" | "Delete" | "Rename";
@@ -389,6 +510,14 @@ declare global {
             recycleBin: string;
             deleteFromRecycleBinMsg: string;
             emptyRecycleBinMsg: string;
+            restoreSession: string;
+            watchLimitReached: string;
+            unknownHostKey: string;
+            tooLargeToTrash: string;
+            trashItems: string;
+            emptyVolumeTrash: string;
+            liveUpdatesUnavailable: string;
+            restoreTo: string;
         };
     }
 }
@@ -209,7 +209,8 @@ class Util {
         const size = this.getFileSize(attr.size);
 
         return {
-            id: encodeURIComponent(originalPath),
+            // Items trashed from the same path are told apart by their trash entry where there is one
+            id: encodeURIComponent(dirent.trash_id ?? originalPath),
             fullPath: fileType == "App" ? originalPath : name,
             dir: RECYCLE_BIN,
             uuid: crypto.randomUUID(),
@@ -228,6 +229,7 @@ class Util {
             ddate: dirent.deleted_date_ms,
             ddateString: new Date(dirent.deleted_date_ms).toLocaleString(locale, DATE_OPTION),
             originalPath,
+            trashId: dirent.trash_id,
             mimeType: dirent.mime_type,
             actualExtension,
         };
@@ -282,6 +284,20 @@ class Util {
         };
     }
 
+    updateFileStat(file: Mp.MediaFile, attr: Mp.WatchAttribute): Mp.MediaFile {
+        const locale = window.lang;
+        const size = this.getFileSize(attr.size);
+        return {
+            ...file,
+            mdate: attr.mtime_ms,
+            mdateString: new Date(attr.mtime_ms).toLocaleString(locale, DATE_OPTION),
+            cdate: attr.birthtime_ms,
+            cdateString: new Date(attr.birthtime_ms).toLocaleString(locale, DATE_OPTION),
+            size: size.size,
+            sizeString: size.sizeString,
+        };
+    }
+
     getRealPath(file: Mp.MediaFile) {
         return file.linkPath ? file.linkPath : file.fullPath;
     }
@@ -467,20 +483,6 @@ class Util {
         };
     };
 
-    toVideoThumbnail = async (fullPath: string) => {
-        const raw = await ipc.invoke("to_thumbnail", { full_path: fullPath, width: 100, height: 100 });
-        const uint8 = Uint8Array.from(raw);
-        const base64 = uint8.toBase64();
-        return `data:image/jpeg;base64,${base64}`;
-    };
-
-    toImageThumbnail = async (fullPath: string) => {
-        const raw = await ipc.invoke("to_image_thumbnail", fullPath);
-        const uint8 = Uint8Array.from(raw);
-        const base64 = uint8.toBase64();
-        return `data:image/jpeg;base64,${base64}`;
-    };
-
     getColumnLabel = (sortKey: Mp.SortKey) => {
         switch (sortKey) {
             case "cdate":
@@ -7,6 +7,7 @@ import { RenameUpdater } from "../states/renameState.svelte";
 import { ClipUpdater } from "../states/clipState.svelte";
 import { driveState, DriveUpdater } from "../states/driveState.svelte";
 import { headerState, HeaderUpdater } from "../states/headerState.svelte";
+import { tabState, TabUpdater } from "../states/tabState.svelte";
 import { SlidUpdater, slideState } from "../states/slideState.svelte";
 import { PreferenceAction, settings, SettingsUpdater } from "../states/settingsState.svelte";
 export { listState } from "../states/listState.svelte";
@@ -14,6 +15,7 @@ export { renameState } from "../states/renameState.svelte";
 export { clipState } from "../states/clipState.svelte";
 export { driveState } from "../states/driveState.svelte";
 export { headerState } from "../states/headerState.svelte";
+export { tabState } from "../states/tabState.svelte";
 export { slideState } from "../states/slideState.svelte";
 export { settings } from "../states/settingsState.svelte";
 export const icons: Mp.IconCache = $state({ cache: {} });
@@ -82,6 +84,7 @@ type AppAction =
     | { type: "setBounds"; value: Mp.Bounds }
     | { type: "pathEditing"; value: boolean }
     | { type: "startSearch" }
+    | { type: "searchKey"; value: string }
     | { type: "endSearch" }
     | { type: "calculateColumnWidths"; value: Mp.MediaFile[] }
     | { type: "adjustColumnWidth"; value: Mp.SortKey }
@@ -90,6 +93,10 @@ type AppAction =
     | { type: "updateSortType"; value: Mp.SortKey }
     | { type: "replaceFiles"; value: Mp.MediaFile[] }
     | { type: "sortInPlace"; value: Mp.MediaFile[] }
+    | { type: "restoreSortType"; value: Mp.SortType }
+    | { type: "tabs"; value: Mp.WindowTabs }
+    | { type: "updateTab"; value: Mp.Tab }
+    | { type: "hoverTabId"; value: number }
     | { type: "preventBlur"; value: boolean }
     | { type: "selectedId"; value: string }
     | { type: "setSelectedIds"; value: string[] }
@@ -180,6 +187,22 @@ const updater = (state: AppState, action: AppAction): AppState => {
             ListUpdater.sort(action.value);
             return state;
 
+        case "restoreSortType":
+            ListUpdater.restoreSortType(action.value);
+            return state;
+
+        case "tabs":
+            TabUpdater.setTabs(action.value);
+            return state;
+
+        case "updateTab":
+            TabUpdater.updateTab(action.value);
+            return state;
+
+        case "hoverTabId":
+            tabState.hoverTabId = action.value;
+            return state;
+
         case "drives":
             DriveUpdater.updateDrives(action.value);
             return state;
@@ -192,6 +215,10 @@ const updater = (state: AppState, action: AppAction): AppState => {
             HeaderUpdater.resetSearch();
             return state;
 
+        case "searchKey":
+            HeaderUpdater.setSearchKey(action.value);
+            return state;
+
         case "incremental":
             return { ...state, incrementalKey: action.value };
 